@@ -0,0 +1,231 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+/// A Slurm time value such as a partition's `MaxTime` or a job's `TimeLimit`.
+///
+/// `Unlimited` sorts after every finite duration, so `requested <= limit` does the right
+/// thing for unlimited partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SlurmDuration {
+    /// A finite duration in seconds.
+    Finite(u64),
+    Unlimited,
+}
+
+impl SlurmDuration {
+    pub fn from_secs(seconds: u64) -> Self {
+        SlurmDuration::Finite(seconds)
+    }
+
+    /// `None` when the minutes do not fit in a duration.
+    pub fn from_minutes(minutes: u64) -> Option<Self> {
+        minutes.checked_mul(60).map(SlurmDuration::Finite)
+    }
+
+    /// Seconds, or `None` when unlimited.
+    pub fn as_secs(&self) -> Option<u64> {
+        match self {
+            SlurmDuration::Finite(seconds) => Some(*seconds),
+            SlurmDuration::Unlimited => None,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        *self == SlurmDuration::Unlimited
+    }
+
+    /// Parses a value that may also be `NONE` (Slurm's "not set"), which yields `None`.
+    pub fn parse_optional(value: &str) -> Result<Option<Self>, String> {
+        match value.trim() {
+            "" | "NONE" | "None" | "N/A" | "(null)" => Ok(None),
+            value => value.parse().map(Some),
+        }
+    }
+
+    /// Formats like Slurm does: `[days-]hours:minutes:seconds`, or `UNLIMITED`.
+    pub fn to_slurm_string(&self) -> String {
+        match self {
+            SlurmDuration::Unlimited => "UNLIMITED".to_string(),
+            SlurmDuration::Finite(seconds) => {
+                let (days, hours, minutes, seconds) = split(*seconds);
+                if days > 0 {
+                    format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+                } else {
+                    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+                }
+            }
+        }
+    }
+}
+
+fn split(seconds: u64) -> (u64, u64, u64, u64) {
+    (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60)
+}
+
+impl FromStr for SlurmDuration {
+    type Err = String;
+
+    /// Accepts every format Slurm uses for time limits: `minutes`, `minutes:seconds`,
+    /// `hours:minutes:seconds`, `days-hours`, `days-hours:minutes` and
+    /// `days-hours:minutes:seconds`, plus `UNLIMITED`/`INFINITE`. Fractional seconds as printed
+    /// by sacct (`01:02.345`) are rounded down. The compact form produced by `Display`
+    /// (`2d`, `2h30m`) is accepted too, which is handy on the command line.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("UNLIMITED") || value.eq_ignore_ascii_case("INFINITE") {
+            return Ok(SlurmDuration::Unlimited);
+        }
+        if value.ends_with(['d', 'h', 'm', 's']) {
+            return parse_compact(value);
+        }
+
+        let invalid = || format!("Invalid Slurm time '{}'", value);
+        let number = |part: &str| -> Result<u64, String> {
+            let whole = part.split_once('.').map_or(part, |(whole, _)| whole);
+            if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            whole.parse().map_err(|_| invalid())
+        };
+
+        let (days, clock) = match value.split_once('-') {
+            Some((days, clock)) => (Some(number(days)?), clock),
+            None => (None, value),
+        };
+        let parts = clock
+            .split(':')
+            .map(number)
+            .collect::<Result<Vec<u64>, String>>()?;
+
+        let (hours, minutes, seconds) = match (days.is_some(), parts.as_slice()) {
+            // days-hours[:minutes[:seconds]]
+            (true, [h]) => (*h, 0, 0),
+            (true, [h, m]) => (*h, *m, 0),
+            (true, [h, m, s]) => (*h, *m, *s),
+            // minutes, minutes:seconds, hours:minutes:seconds
+            (false, [m]) => (0, *m, 0),
+            (false, [m, s]) => (0, *m, *s),
+            (false, [h, m, s]) => (*h, *m, *s),
+            _ => return Err(invalid()),
+        };
+
+        [(days.unwrap_or(0), 86400), (hours, 3600), (minutes, 60), (seconds, 1)]
+            .into_iter()
+            .try_fold(0u64, |total, (value, unit)| value.checked_mul(unit)?.checked_add(total))
+            .map(SlurmDuration::Finite)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Parses the compact `1d12h30m15s` form; every unit is optional but their order is fixed.
+fn parse_compact(value: &str) -> Result<SlurmDuration, String> {
+    let invalid = || format!("Invalid duration '{}'", value);
+    let mut seconds: u64 = 0;
+    let mut rest = value;
+    let mut units = [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)].into_iter();
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        if digits == 0 {
+            return Err(invalid());
+        }
+        let number: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let unit = rest[digits..].chars().next().ok_or_else(invalid)?;
+        let (_, multiplier) = units.find(|(u, _)| *u == unit).ok_or_else(invalid)?;
+        seconds = number
+            .checked_mul(multiplier)
+            .and_then(|unit_seconds| seconds.checked_add(unit_seconds))
+            .ok_or_else(invalid)?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+
+    Ok(SlurmDuration::Finite(seconds))
+}
+
+impl fmt::Display for SlurmDuration {
+    /// Compact human form: `7d`, `2h30m`, `1d12h`, `45s`, `unlimited`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = match self {
+            SlurmDuration::Unlimited => return write!(f, "unlimited"),
+            SlurmDuration::Finite(0) => return write!(f, "0s"),
+            SlurmDuration::Finite(seconds) => *seconds,
+        };
+
+        let (days, hours, minutes, seconds) = split(seconds);
+        let mut out = String::new();
+        for (value, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")] {
+            if value > 0 {
+                out.push_str(&format!("{}{}", value, unit));
+            }
+        }
+        write!(f, "{}", out)
+    }
+}
+
+/// Serializes as the Slurm string (`7-00:00:00`, `UNLIMITED`) so consumers can parse it with
+/// the same rules as scontrol output.
+impl Serialize for SlurmDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_slurm_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slurm_duration() {
+        let test_cases = vec![
+            ("30", SlurmDuration::from_minutes(30).unwrap()),
+            ("30:15", SlurmDuration::Finite(30 * 60 + 15)),
+            ("2:00:00", SlurmDuration::Finite(2 * 3600)),
+            ("7-00:00:00", SlurmDuration::Finite(7 * 86400)),
+            ("1-12", SlurmDuration::Finite(86400 + 12 * 3600)),
+            ("1-12:30", SlurmDuration::Finite(86400 + 12 * 3600 + 30 * 60)),
+            ("01:02.345", SlurmDuration::Finite(62)),
+            ("UNLIMITED", SlurmDuration::Unlimited),
+            ("INFINITE", SlurmDuration::Unlimited),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(input.parse::<SlurmDuration>().unwrap(), expected, "input: {}", input);
+        }
+
+        for input in ["", "abc", "1:2:3:4", "1-", "-1", "1--2"] {
+            assert!(input.parse::<SlurmDuration>().is_err(), "input: {}", input);
+        }
+
+        // Too large for a u64 of seconds
+        for input in ["999999999999999999-00:00:00", "99999999999999999:00:00"] {
+            assert!(input.parse::<SlurmDuration>().is_err(), "input: {}", input);
+        }
+        assert_eq!(SlurmDuration::from_minutes(u64::MAX), None);
+
+        assert_eq!(SlurmDuration::parse_optional("NONE").unwrap(), None);
+
+        assert_eq!("2d".parse::<SlurmDuration>().unwrap(), SlurmDuration::Finite(2 * 86400));
+        assert_eq!("2h30m".parse::<SlurmDuration>().unwrap(), SlurmDuration::Finite(9000));
+        for input in ["d", "2x", "30m2h", "2h2h", "1.5d", "2µs", "2é", "999999999999999999d"] {
+            assert!(input.parse::<SlurmDuration>().is_err(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_order_and_display() {
+        let two_days: SlurmDuration = "2-00:00:00".parse().unwrap();
+        let week: SlurmDuration = "7-00:00:00".parse().unwrap();
+        assert!(two_days < week);
+        assert!(week < SlurmDuration::Unlimited);
+
+        assert_eq!(week.to_string(), "7d");
+        assert_eq!("2:30:00".parse::<SlurmDuration>().unwrap().to_string(), "2h30m");
+        assert_eq!(SlurmDuration::Unlimited.to_string(), "unlimited");
+        assert_eq!(SlurmDuration::Finite(0).to_string(), "0s");
+
+        assert_eq!(week.to_slurm_string(), "7-00:00:00");
+        assert_eq!("90".parse::<SlurmDuration>().unwrap().to_slurm_string(), "01:30:00");
+    }
+}
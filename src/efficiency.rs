@@ -0,0 +1,330 @@
+//! How much of what a job asked for it actually used, like `seff`, computed from sacct records.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::duration::SlurmDuration;
+use crate::job_state::JobState;
+use crate::memory::{DisplayUnits, Memory};
+use crate::sacct::{AccountingRecord, ExitCode};
+use crate::table::{Cell, Value};
+
+/// Columns `JobEfficiency::value` knows, for the `efficiency` table.
+pub const EFFICIENCY_COLUMNS: &[&str] = &[
+    "job_id", "name", "user", "account", "partition", "state", "elapsed", "nodes", "cpus",
+    "cpu_used", "cpu_eff", "mem_used", "mem_req", "mem_eff", "gpus", "gpu_hours",
+];
+
+/// Columns `UserEfficiency::value` knows, for the worst offenders table.
+pub const USER_EFFICIENCY_COLUMNS: &[&str] =
+    &["user", "jobs", "cpu_eff", "mem_eff", "core_hours", "idle_core_hours", "mem_req", "mem_used", "gpu_hours"];
+
+/// Efficiency of one job allocation. Percentages are 0-100 and `None` when the job has not
+/// run or sacct has no usage data for it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobEfficiency {
+    pub job_id: String,
+    pub name: Option<String>,
+    pub user: Option<String>,
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    pub state: Option<JobState>,
+    pub exit_code: Option<ExitCode>,
+    pub elapsed: SlurmDuration,
+    pub nodes: u32,
+    pub cpus: u32,
+    /// `TotalCPU`: user plus system time of every task.
+    pub cpu_used: Option<SlurmDuration>,
+    /// `cpu_used / (elapsed × cpus)`
+    pub cpu_efficiency: Option<f64>,
+    /// The largest `MaxRSS` of any step. sacct reports the peak of the largest task, which
+    /// for one task per node is the node's usage.
+    pub mem_used: Option<Memory>,
+    /// Requested memory per node, whether the job asked per CPU, per node or in total.
+    pub mem_requested: Option<Memory>,
+    /// `mem_used / mem_requested`
+    pub mem_efficiency: Option<f64>,
+    pub gpus: u64,
+    pub gpu_hours: f64,
+}
+
+impl JobEfficiency {
+    /// Computes the efficiency of `allocation` from its own record and its `steps`.
+    pub fn new(allocation: &AccountingRecord, steps: &[&AccountingRecord]) -> Self {
+        let elapsed = allocation.elapsed.unwrap_or(SlurmDuration::Finite(0));
+        let seconds = elapsed.as_secs().unwrap_or(0);
+        let cpus = allocation.num_cpus.unwrap_or(0);
+        let gpus = allocation.alloc_tres.as_ref().and_then(|tres| tres.gres("gpu")).unwrap_or(0);
+
+        let cpu_used = allocation.total_cpu;
+        let core_seconds = seconds * u64::from(cpus);
+        let cpu_efficiency = cpu_used
+            .and_then(|used| used.as_secs())
+            .filter(|_| core_seconds > 0)
+            .map(|used| percent(used as f64, core_seconds as f64));
+
+        let mem_used = steps.iter().filter_map(|step| step.max_rss).max();
+        let mem_requested = allocation.requested_memory_per_node();
+        let mem_efficiency = match (mem_used, mem_requested) {
+            (Some(used), Some(requested)) if !requested.is_zero() => {
+                Some(percent(used.as_bytes() as f64, requested.as_bytes() as f64))
+            }
+            _ => None,
+        };
+
+        JobEfficiency {
+            job_id: allocation.job_id.clone(),
+            name: allocation.name.clone(),
+            user: allocation.user.clone(),
+            account: allocation.account.clone(),
+            partition: allocation.partition.clone(),
+            state: allocation.state.clone(),
+            exit_code: allocation.exit_code,
+            elapsed,
+            nodes: allocation.num_nodes.unwrap_or(0),
+            cpus,
+            cpu_used,
+            cpu_efficiency,
+            mem_used,
+            mem_requested,
+            mem_efficiency,
+            gpus,
+            gpu_hours: gpus as f64 * seconds as f64 / 3600.0,
+        }
+    }
+
+    /// Allocated core hours: elapsed time times CPUs.
+    pub fn core_hours(&self) -> f64 {
+        self.elapsed.as_secs().unwrap_or(0) as f64 * f64::from(self.cpus) / 3600.0
+    }
+
+    /// A `seff`-style report, one line per field.
+    pub fn fields(&self, units: DisplayUnits) -> Vec<(String, String)> {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        let efficiency = |value: Option<f64>, of: String| {
+            value.map_or_else(|| "-".to_string(), |value| format!("{:.2}% of {}", value, of))
+        };
+        let mut state = optional(self.state.as_ref().map(JobState::to_string));
+        if let Some(exit_code) = self.exit_code {
+            state = format!("{} (exit code {})", state, exit_code);
+        }
+
+        vec![
+            ("Job ID".to_string(), self.job_id.clone()),
+            ("Name".to_string(), optional(self.name.clone())),
+            ("User/Account".to_string(), format!("{}/{}", optional(self.user.clone()), optional(self.account.clone()))),
+            ("State".to_string(), state),
+            ("Nodes".to_string(), self.nodes.to_string()),
+            ("Cores".to_string(), self.cpus.to_string()),
+            ("Elapsed".to_string(), self.elapsed.to_string()),
+            ("CPU Utilized".to_string(), optional(self.cpu_used.map(|used| used.to_string()))),
+            (
+                "CPU Efficiency".to_string(),
+                efficiency(self.cpu_efficiency, format!("{:.2} core hours", self.core_hours())),
+            ),
+            ("Memory Utilized".to_string(), units.format_optional(self.mem_used)),
+            (
+                "Memory Efficiency".to_string(),
+                efficiency(
+                    self.mem_efficiency,
+                    format!("{} per node", units.format_optional(self.mem_requested)),
+                ),
+            ),
+            ("GPU Hours".to_string(), format!("{:.2}", self.gpu_hours)),
+        ]
+    }
+
+    /// The value of one of `EFFICIENCY_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        let text = |value: Option<&String>| Value::from_option(value.cloned(), Value::Text);
+
+        match column {
+            "job_id" => Value::Text(self.job_id.clone()),
+            "name" => text(self.name.as_ref()),
+            "user" => text(self.user.as_ref()),
+            "account" => text(self.account.as_ref()),
+            "partition" => text(self.partition.as_ref()),
+            "state" => Value::from_option(self.state.as_ref(), |s| Value::Text(s.to_string())),
+            "elapsed" => Value::Duration(self.elapsed),
+            "nodes" => Value::Count(self.nodes.into()),
+            "cpus" => Value::Count(self.cpus.into()),
+            "cpu_used" => Value::from_option(self.cpu_used, Value::Duration),
+            "cpu_eff" => Value::from_option(self.cpu_efficiency, Value::Float),
+            "mem_used" => Value::from_option(self.mem_used, Value::Memory),
+            "mem_req" => Value::from_option(self.mem_requested, Value::Memory),
+            "mem_eff" => Value::from_option(self.mem_efficiency, Value::Float),
+            "gpus" => Value::Count(self.gpus),
+            "gpu_hours" => Value::Float(self.gpu_hours),
+            _ => Value::Empty,
+        }
+    }
+
+    /// Like `value`, with the state coloured by state and efficiencies by how poor they are.
+    pub fn cell(&self, column: &str) -> Cell {
+        match (column, &self.state) {
+            ("state", Some(state)) => Cell::colored(self.value(column), state.color()),
+            ("cpu_eff", _) => efficiency_cell(self.value(column), self.cpu_efficiency),
+            ("mem_eff", _) => efficiency_cell(self.value(column), self.mem_efficiency),
+            _ => Cell::new(self.value(column)),
+        }
+    }
+}
+
+/// One efficiency per job allocation in `records`, in record order. Jobs that never started
+/// are left out.
+pub fn job_efficiencies(records: &[AccountingRecord]) -> Vec<JobEfficiency> {
+    let mut steps: BTreeMap<&str, Vec<&AccountingRecord>> = BTreeMap::new();
+    for step in records.iter().filter(|record| record.is_step()) {
+        steps.entry(step.job_id.as_str()).or_default().push(step);
+    }
+
+    records
+        .iter()
+        .filter(|record| !record.is_step() && record.start_time.is_some())
+        .map(|allocation| {
+            let steps = steps.get(allocation.job_id.as_str()).map_or(&[][..], Vec::as_slice);
+            JobEfficiency::new(allocation, steps)
+        })
+        .collect()
+}
+
+/// The efficiency of all of one user's jobs, weighted by what each job requested.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserEfficiency {
+    pub user: String,
+    pub jobs: usize,
+    pub core_hours: f64,
+    /// Allocated core hours in which no CPU time was used.
+    pub idle_core_hours: f64,
+    pub cpu_efficiency: Option<f64>,
+    /// Summed over the user's jobs that report memory usage, per node.
+    pub mem_requested: Memory,
+    pub mem_used: Memory,
+    pub mem_efficiency: Option<f64>,
+    pub gpu_hours: f64,
+}
+
+impl UserEfficiency {
+    /// The value of one of `USER_EFFICIENCY_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        match column {
+            "user" => Value::Text(self.user.clone()),
+            "jobs" => Value::Count(self.jobs as u64),
+            "cpu_eff" => Value::from_option(self.cpu_efficiency, Value::Float),
+            "mem_eff" => Value::from_option(self.mem_efficiency, Value::Float),
+            "core_hours" => Value::Float(self.core_hours),
+            "idle_core_hours" => Value::Float(self.idle_core_hours),
+            "mem_req" => Value::Memory(self.mem_requested),
+            "mem_used" => Value::Memory(self.mem_used),
+            "gpu_hours" => Value::Float(self.gpu_hours),
+            _ => Value::Empty,
+        }
+    }
+
+    pub fn cell(&self, column: &str) -> Cell {
+        match column {
+            "cpu_eff" => efficiency_cell(self.value(column), self.cpu_efficiency),
+            "mem_eff" => efficiency_cell(self.value(column), self.mem_efficiency),
+            _ => Cell::new(self.value(column)),
+        }
+    }
+}
+
+/// Totals per user, worst first: the users who leave the most requested memory unused, then
+/// the most allocated cores idle.
+pub fn worst_offenders(jobs: &[JobEfficiency]) -> Vec<UserEfficiency> {
+    let mut users: BTreeMap<&str, Vec<&JobEfficiency>> = BTreeMap::new();
+    for job in jobs {
+        users.entry(job.user.as_deref().unwrap_or("(unknown)")).or_default().push(job);
+    }
+
+    let mut offenders: Vec<UserEfficiency> = users
+        .into_iter()
+        .map(|(user, jobs)| {
+            let core_hours: f64 = jobs.iter().map(|job| job.core_hours()).sum();
+            let used_core_hours: f64 = jobs
+                .iter()
+                .filter_map(|job| job.cpu_used.and_then(|used| used.as_secs()))
+                .map(|seconds| seconds as f64 / 3600.0)
+                .sum();
+            let with_memory = || jobs.iter().filter(|job| job.mem_efficiency.is_some());
+            let mem_requested: Memory = with_memory().filter_map(|job| job.mem_requested).sum();
+            let mem_used: Memory = with_memory().filter_map(|job| job.mem_used).sum();
+
+            UserEfficiency {
+                user: user.to_string(),
+                jobs: jobs.len(),
+                core_hours,
+                idle_core_hours: (core_hours - used_core_hours).max(0.0),
+                cpu_efficiency: (core_hours > 0.0).then(|| percent(used_core_hours, core_hours)),
+                mem_requested,
+                mem_used,
+                mem_efficiency: (!mem_requested.is_zero())
+                    .then(|| percent(mem_used.as_bytes() as f64, mem_requested.as_bytes() as f64)),
+                gpu_hours: jobs.iter().map(|job| job.gpu_hours).sum(),
+            }
+        })
+        .collect();
+
+    let unused = |user: &UserEfficiency| user.mem_requested - user.mem_used;
+    offenders.sort_by(|a, b| {
+        unused(b)
+            .cmp(&unused(a))
+            .then(b.idle_core_hours.total_cmp(&a.idle_core_hours))
+    });
+    offenders
+}
+
+fn percent(part: f64, whole: f64) -> f64 {
+    part / whole * 100.0
+}
+
+/// Red below 25%, yellow below 50%.
+fn efficiency_cell(value: Value, efficiency: Option<f64>) -> Cell {
+    match efficiency {
+        Some(efficiency) if efficiency < 25.0 => Cell::colored(value, termcolor::Color::Red),
+        Some(efficiency) if efficiency < 50.0 => Cell::colored(value, termcolor::Color::Yellow),
+        _ => Cell::new(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sacct::parse_sacct;
+
+    const SACCT: &str = "\
+JobID|User|State|Elapsed|TotalCPU|MaxRSS|ReqMem|ReqTRES|AllocTRES|NNodes|NCPUS|Start
+1001|alice|COMPLETED|02:00:00|04:00:00||500G|cpu=8,mem=500G,node=1|cpu=8,mem=500G,node=1,gres/gpu=2|1|8|2024-01-01T08:00:00
+1001.batch||COMPLETED|02:00:00|04:00:00|4G||||1|8|2024-01-01T08:00:00
+1001.extern||COMPLETED|02:00:00|00:00:00|1024K||||1|8|2024-01-01T08:00:00
+1002|bob|COMPLETED|01:00:00|07:00:00||4000Mc|cpu=8,mem=32000M,node=2|cpu=8,mem=32000M,node=2|2|8|2024-01-01T09:00:00
+1002.0||COMPLETED|01:00:00|07:00:00|12000M||||2|8|2024-01-01T09:00:00
+1003|bob|PENDING|00:00:00|00:00:00||1G|cpu=1,mem=1G,node=1||1|1|Unknown
+";
+
+    #[test]
+    fn test_job_efficiencies() {
+        let jobs = job_efficiencies(&parse_sacct(SACCT).unwrap());
+        assert_eq!(jobs.len(), 2);
+
+        let wasteful = &jobs[0];
+        assert_eq!(wasteful.cpu_efficiency, Some(25.0));
+        assert_eq!(wasteful.mem_used, Some("4G".parse().unwrap()));
+        assert_eq!(wasteful.mem_efficiency, Some(0.8));
+        assert_eq!(wasteful.gpu_hours, 4.0);
+
+        // 4000M per CPU, 8 CPUs over 2 nodes
+        let per_cpu = &jobs[1];
+        assert_eq!(per_cpu.mem_requested, Some("16000M".parse().unwrap()));
+        assert_eq!(per_cpu.mem_efficiency, Some(75.0));
+        assert_eq!(per_cpu.cpu_efficiency, Some(87.5));
+
+        let offenders = worst_offenders(&jobs);
+        assert_eq!(offenders[0].user, "alice");
+        assert_eq!(offenders[0].idle_core_hours, 12.0);
+        assert_eq!(offenders[1].user, "bob");
+        assert_eq!(offenders[1].value("mem_eff"), Value::Float(75.0));
+    }
+}
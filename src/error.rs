@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Everything that can go wrong while fetching or parsing Slurm data.
+///
+/// The variants separate problems with the local installation (`MissingBinary`) from problems
+/// reported by Slurm itself (`CommandFailed`, e.g. the controller being down) and from
+/// malformed data (`Parse`, `Json`, `Hostlist`).
+#[derive(Debug, Error)]
+pub enum SlurmToolError {
+    /// The Slurm command is not installed or not on `PATH`.
+    #[error("{program} not found; is Slurm installed and on PATH?")]
+    MissingBinary { program: String },
+
+    /// The command exists but could not be started.
+    #[error("Failed to execute {program}: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The command ran and exited unsuccessfully; `code` is `None` when it was killed by a
+    /// signal.
+    #[error("{program} exited with {}: {}", describe_exit(.code), .stderr.trim())]
+    CommandFailed {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+
+    #[error("{program} printed invalid UTF-8: {source}")]
+    Utf8 {
+        program: String,
+        #[source]
+        source: std::string::FromUtf8Error,
+    },
+
+    #[error("Failed to read {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A record that could not be understood. `line` is 1-based.
+    #[error("{message}{}", location(.line, .field))]
+    Parse {
+        line: Option<usize>,
+        field: Option<String>,
+        message: String,
+    },
+
+    #[error("Failed to parse {what} JSON: {source}")]
+    Json {
+        what: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{0}")]
+    Hostlist(String),
+
+    #[error("Cannot mix JSON and oneliner {entity} records in --input files")]
+    MixedInput { entity: &'static str },
+
+    #[error("Duplicate {kind} name: {name}")]
+    Duplicate { kind: &'static str, name: String },
+
+    #[error("Partition '{0}' not found")]
+    UnknownPartition(String),
+
+    #[error("Node '{0}' not found")]
+    UnknownNode(String),
+
+    #[error("Unknown column '{column}', expected one of: {available}")]
+    UnknownColumn { column: String, available: String },
+
+    #[error("Failed to write output: {0}")]
+    Output(String),
+
+    /// A query ran fine but its filters left nothing to show.
+    #[error("No {0} matched the given filters")]
+    NoMatch(&'static str),
+
+    /// The `SlurmSource` does not implement the command.
+    #[error("This source cannot provide {program} output")]
+    Unsupported { program: &'static str },
+}
+
+/// Shorthand for results carrying a `SlurmToolError`.
+pub type Result<T, E = SlurmToolError> = std::result::Result<T, E>;
+
+impl SlurmToolError {
+    /// A parse error without line or field context.
+    pub fn parse(message: impl Into<String>) -> Self {
+        SlurmToolError::Parse { line: None, field: None, message: message.into() }
+    }
+}
+
+/// Process exit codes of the `slurmtool` binary, one per failure class, so scripts can
+/// branch on why a run failed.
+pub mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// Any failure without a more specific code.
+    pub const FAILURE: u8 = 1;
+    /// Invalid command line arguments (the code clap uses), e.g. an unknown column.
+    pub const USAGE: u8 = 2;
+    /// The filters matched nothing.
+    pub const NO_MATCH: u8 = 3;
+    /// The requested partition or node does not exist.
+    pub const NOT_FOUND: u8 = 4;
+    /// Slurm output or an input file could not be parsed.
+    pub const PARSE: u8 = 5;
+    /// A Slurm command is not installed.
+    pub const MISSING_BINARY: u8 = 6;
+    /// The Slurm controller could not be reached.
+    pub const CONTROLLER_UNREACHABLE: u8 = 7;
+    /// A Slurm command failed for another reason.
+    pub const SLURM_ERROR: u8 = 8;
+    /// An input file could not be read.
+    pub const IO: u8 = 9;
+}
+
+impl SlurmToolError {
+    /// The process exit code for this error; see [`exit_code`].
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SlurmToolError::MissingBinary { .. } => exit_code::MISSING_BINARY,
+            SlurmToolError::CommandFailed { stderr, .. } if is_controller_unreachable(stderr) => {
+                exit_code::CONTROLLER_UNREACHABLE
+            }
+            SlurmToolError::Spawn { .. } | SlurmToolError::CommandFailed { .. } => exit_code::SLURM_ERROR,
+            SlurmToolError::Utf8 { .. }
+            | SlurmToolError::Parse { .. }
+            | SlurmToolError::Json { .. }
+            | SlurmToolError::Hostlist(_)
+            | SlurmToolError::MixedInput { .. }
+            | SlurmToolError::Duplicate { .. } => exit_code::PARSE,
+            SlurmToolError::Read { .. } => exit_code::IO,
+            SlurmToolError::UnknownPartition(_) | SlurmToolError::UnknownNode(_) => exit_code::NOT_FOUND,
+            SlurmToolError::NoMatch(_) => exit_code::NO_MATCH,
+            SlurmToolError::UnknownColumn { .. } => exit_code::USAGE,
+            SlurmToolError::Output(_) | SlurmToolError::Unsupported { .. } => exit_code::FAILURE,
+        }
+    }
+}
+
+/// Whether Slurm's error output says the controller is down or unreachable.
+fn is_controller_unreachable(stderr: &str) -> bool {
+    const MESSAGES: [&str; 3] = [
+        "Unable to contact slurm controller",
+        "Zero Bytes were transmitted or received",
+        "Socket timed out on send/recv operation",
+    ];
+    MESSAGES.iter().any(|message| stderr.contains(message))
+}
+
+fn describe_exit(code: &Option<i32>) -> String {
+    code.map_or_else(|| "a signal".to_string(), |code| format!("status {}", code))
+}
+
+fn location(line: &Option<usize>, field: &Option<String>) -> String {
+    match (line, field) {
+        (Some(line), Some(field)) => format!(" (line {}, field {})", line, field),
+        (Some(line), None) => format!(" (line {})", line),
+        (None, Some(field)) => format!(" (field {})", field),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_messages() {
+        let error = SlurmToolError::CommandFailed {
+            program: "scontrol".to_string(),
+            code: Some(1),
+            stderr: "slurm_load_node error: Unable to contact slurm controller\n".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "scontrol exited with status 1: slurm_load_node error: Unable to contact slurm controller"
+        );
+
+        let error = SlurmToolError::Parse {
+            line: Some(3),
+            field: Some("NodeName".to_string()),
+            message: "Record does not start with NodeName=".to_string(),
+        };
+        assert_eq!(error.to_string(), "Record does not start with NodeName= (line 3, field NodeName)");
+
+        assert_eq!(SlurmToolError::parse("bad").to_string(), "bad");
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let failed = |stderr: &str| SlurmToolError::CommandFailed {
+            program: "scontrol".to_string(),
+            code: Some(1),
+            stderr: stderr.to_string(),
+        };
+
+        assert_eq!(
+            failed("slurm_load_partitions: Unable to contact slurm controller (connect failure)")
+                .exit_code(),
+            exit_code::CONTROLLER_UNREACHABLE
+        );
+        assert_eq!(failed("Invalid user for SlurmUser").exit_code(), exit_code::SLURM_ERROR);
+        assert_eq!(
+            SlurmToolError::MissingBinary { program: "scontrol".to_string() }.exit_code(),
+            exit_code::MISSING_BINARY
+        );
+        assert_eq!(SlurmToolError::UnknownPartition("gpu".to_string()).exit_code(), exit_code::NOT_FOUND);
+        assert_eq!(SlurmToolError::parse("bad").exit_code(), exit_code::PARSE);
+        assert_eq!(SlurmToolError::NoMatch("nodes").exit_code(), exit_code::NO_MATCH);
+    }
+}
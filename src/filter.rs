@@ -0,0 +1,463 @@
+//! The `--where` expression language for selecting nodes.
+//!
+//! ```text
+//! state=idle && cpu_free>=32 && mem_free>=256G && feature=avx512
+//! (gres=gpu:a100 || gres=gpu:h100) && !state=drain
+//! name=~'^gpu[0-9]+$' && idle_for>2d
+//! ```
+//!
+//! A comparison is `attribute op value`. Attributes are the `nodes --columns` names plus the
+//! singular aliases `feature` and `partition`. How the value is read depends on the attribute:
+//! memory takes units (`256G`, bare numbers are megabytes), durations take Slurm or compact
+//! times (`2d`, `1-12:00:00`), counts take numbers. `state=drain` matches the base state or
+//! any flag, like `nodes --state`. For list attributes (`feature`, `partition`, `gres`) `=`
+//! means "contains"; `gres=gpu` matches any GPU, `gres=gpu:a100` only A100s.
+//!
+//! Operators: `=` (or `==`), `!=`, `<`, `<=`, `>`, `>=`, `=~` and `!~` (regular expressions),
+//! combined with `&&`, `||`, `!` and parentheses. Values containing spaces or operator
+//! characters can be quoted with `'` or `"`. A comparison against a value the node does not
+//! have (e.g. `idle_for` on a busy node) is false, except for `!=` and `!~`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use regex::Regex;
+
+use crate::duration::SlurmDuration;
+use crate::memory::{DisplayUnits, Memory};
+use crate::node::{Node, NODE_COLUMNS};
+use crate::table::Value;
+
+/// A parsed `--where` expression.
+#[derive(Debug, Clone)]
+pub enum NodeFilter {
+    And(Box<NodeFilter>, Box<NodeFilter>),
+    Or(Box<NodeFilter>, Box<NodeFilter>),
+    Not(Box<NodeFilter>),
+    Compare(Comparison),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Match => "=~",
+            Op::NotMatch => "!~",
+        };
+        f.write_str(op)
+    }
+}
+
+/// `attribute op value`, with the value already converted for the attribute.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub attribute: String,
+    pub op: Op,
+    operand: Operand,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Text(String),
+    Number(f64),
+    Memory(Memory),
+    Duration(SlurmDuration),
+    Regex(Regex),
+}
+
+/// How an attribute's values are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    /// Comma separated list; `=` tests membership.
+    List,
+    State,
+    Number,
+    Memory,
+    Duration,
+}
+
+fn kind(attribute: &str) -> Option<Kind> {
+    let kind = match attribute {
+        "state" => Kind::State,
+        "feature" | "features" | "partition" | "partitions" | "gres" | "gres_used" | "gres_free" => {
+            Kind::List
+        }
+        "cpu_total" | "cpu_alloc" | "cpu_free" | "cpu_load" => Kind::Number,
+        "mem_total" | "mem_alloc" | "mem_free" => Kind::Memory,
+        "uptime" | "idle_for" => Kind::Duration,
+        attribute if NODE_COLUMNS.contains(&attribute) => Kind::Text,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// The `Node::value` column behind an attribute name.
+fn column(attribute: &str) -> &str {
+    match attribute {
+        "feature" => "features",
+        "partition" => "partitions",
+        attribute => attribute,
+    }
+}
+
+impl NodeFilter {
+    /// Whether `node` satisfies the expression; `now` is used for `uptime` and `idle_for`.
+    pub fn matches(&self, node: &Node, now: &DateTime<Local>) -> bool {
+        match self {
+            NodeFilter::And(a, b) => a.matches(node, now) && b.matches(node, now),
+            NodeFilter::Or(a, b) => a.matches(node, now) || b.matches(node, now),
+            NodeFilter::Not(inner) => !inner.matches(node, now),
+            NodeFilter::Compare(comparison) => comparison.matches(node, now),
+        }
+    }
+}
+
+impl Comparison {
+    fn matches(&self, node: &Node, now: &DateTime<Local>) -> bool {
+        let negated = matches!(self.op, Op::Ne | Op::NotMatch);
+
+        if let (Some(Kind::State), Operand::Text(wanted)) = (kind(&self.attribute), &self.operand) {
+            let matched = node.state.as_ref().is_some_and(|state| state.matches(wanted));
+            return matched != negated;
+        }
+
+        let value = node.value(column(&self.attribute), now);
+        let list = kind(&self.attribute) == Some(Kind::List);
+        match (&self.operand, value) {
+            (_, Value::Empty) => negated,
+            (Operand::Regex(regex), value) if list => {
+                list_items(&value).iter().any(|item| regex.is_match(item)) != negated
+            }
+            (Operand::Regex(regex), value) => regex.is_match(&value.render(DisplayUnits::default())) != negated,
+            (Operand::Text(wanted), value) if list => {
+                list_items(&value).iter().any(|item| list_item_matches(item, wanted)) != negated
+            }
+            (Operand::Text(wanted), value) => (value.render(DisplayUnits::default()) == *wanted) != negated,
+            (Operand::Number(number), Value::Count(count)) => compare(self.op, (count as f64).total_cmp(number)),
+            (Operand::Number(number), Value::Float(float)) => compare(self.op, float.total_cmp(number)),
+            (Operand::Memory(wanted), Value::Memory(memory)) => compare(self.op, memory.cmp(wanted)),
+            (Operand::Duration(wanted), Value::Duration(duration)) => compare(self.op, duration.cmp(wanted)),
+            _ => false,
+        }
+    }
+}
+
+fn compare(op: Op, ordering: Ordering) -> bool {
+    match op {
+        Op::Eq => ordering.is_eq(),
+        Op::Ne => ordering.is_ne(),
+        Op::Lt => ordering.is_lt(),
+        Op::Le => ordering.is_le(),
+        Op::Gt => ordering.is_gt(),
+        Op::Ge => ordering.is_ge(),
+        Op::Match | Op::NotMatch => false,
+    }
+}
+
+fn list_items(value: &Value) -> Vec<String> {
+    match value {
+        Value::Text(text) => text.split(',').map(String::from).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `gpu` matches `gpu:a100:4`, `gpu:a100` matches `gpu:a100:4`; features and partitions
+/// match exactly.
+fn list_item_matches(item: &str, wanted: &str) -> bool {
+    item == wanted
+        || item
+            .strip_prefix(wanted)
+            .is_some_and(|rest| rest.starts_with(':'))
+}
+
+impl FromStr for NodeFilter {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let filter = parser.or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(format!("Unexpected {} in '{}'", token, expression)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        chars.next();
+        let next = chars.peek().copied();
+        let mut take_next = || {
+            chars.next();
+        };
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            ('&', Some('&')) => {
+                take_next();
+                Token::And
+            }
+            ('|', Some('|')) => {
+                take_next();
+                Token::Or
+            }
+            ('=', Some('=')) => {
+                take_next();
+                Token::Op(Op::Eq)
+            }
+            ('=', Some('~')) => {
+                take_next();
+                Token::Op(Op::Match)
+            }
+            ('!', Some('=')) => {
+                take_next();
+                Token::Op(Op::Ne)
+            }
+            ('!', Some('~')) => {
+                take_next();
+                Token::Op(Op::NotMatch)
+            }
+            ('<', Some('=')) => {
+                take_next();
+                Token::Op(Op::Le)
+            }
+            ('>', Some('=')) => {
+                take_next();
+                Token::Op(Op::Ge)
+            }
+            ('=', _) => Token::Op(Op::Eq),
+            ('<', _) => Token::Op(Op::Lt),
+            ('>', _) => Token::Op(Op::Gt),
+            ('!', _) => Token::Not,
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            ('&' | '|', _) => return Err(format!("Use '{0}{0}' instead of '{0}' in '{1}'", c, expression)),
+            ('\'' | '"', _) => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(other) => word.push(other),
+                        None => return Err(format!("Unterminated quote in '{}'", expression)),
+                    }
+                }
+                Token::Word(word)
+            }
+            (c, _) => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "&|=!<>()'\"".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens; `||` binds looser than `&&`, which binds looser than `!`.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<NodeFilter, String> {
+        let mut filter = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            filter = NodeFilter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<NodeFilter, String> {
+        let mut filter = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            filter = NodeFilter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<NodeFilter, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(NodeFilter::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            Some(Token::Word(attribute)) => self.comparison(attribute),
+            Some(token) => Err(format!("Expected an attribute, found {}", token)),
+            None => Err("Expected an attribute, found the end of the expression".to_string()),
+        }
+    }
+
+    fn comparison(&mut self, attribute: String) -> Result<NodeFilter, String> {
+        let kind = kind(&attribute).ok_or_else(|| {
+            format!("Unknown attribute '{}', expected one of: feature, partition, {}", attribute, NODE_COLUMNS.join(", "))
+        })?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("Expected an operator after '{}'", attribute)),
+        };
+        let value = match self.next() {
+            Some(Token::Word(value)) => value,
+            _ => return Err(format!("Expected a value after '{}{}'", attribute, op)),
+        };
+
+        let operand = match (kind, op) {
+            (_, Op::Match | Op::NotMatch) => Operand::Regex(
+                Regex::new(&value).map_err(|e| format!("Invalid regular expression '{}': {}", value, e))?,
+            ),
+            (Kind::Text | Kind::List | Kind::State, Op::Eq | Op::Ne) => Operand::Text(value),
+            (Kind::Text | Kind::List | Kind::State, _) => {
+                return Err(format!("'{}' only supports =, !=, =~ and !~", attribute))
+            }
+            (Kind::Number, _) => Operand::Number(
+                value.parse().map_err(|_| format!("Invalid number '{}' for '{}'", value, attribute))?,
+            ),
+            (Kind::Memory, _) => Operand::Memory(value.parse()?),
+            (Kind::Duration, _) => Operand::Duration(value.parse()?),
+        };
+
+        Ok(NodeFilter::Compare(Comparison { attribute, op, operand }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(fields: &[(&str, &str)]) -> Node {
+        Node::from_fields(fields)
+    }
+
+    fn matches(expression: &str, node: &Node) -> bool {
+        let now = Local::now();
+        expression.parse::<NodeFilter>().unwrap().matches(node, &now)
+    }
+
+    #[test]
+    fn test_filter_nodes() {
+        let idle = node(&[
+            ("NodeName", "node01"),
+            ("State", "IDLE"),
+            ("CPUTot", "64"),
+            ("CPUAlloc", "0"),
+            ("RealMemory", "512000"),
+            ("AllocMem", "0"),
+            ("ActiveFeatures", "avx2,avx512"),
+            ("Gres", "gpu:a100:4"),
+        ]);
+        let drained = node(&[
+            ("NodeName", "node02"),
+            ("State", "MIXED+DRAIN"),
+            ("CPUTot", "64"),
+            ("CPUAlloc", "48"),
+            ("RealMemory", "512000"),
+            ("AllocMem", "400000"),
+            ("ActiveFeatures", "avx2"),
+        ]);
+
+        let expression = "state=idle && cpu_free>=32 && mem_free>=256G && feature=avx512";
+        assert!(matches(expression, &idle));
+        assert!(!matches(expression, &drained));
+
+        assert!(matches("state=drain", &drained));
+        assert!(matches("!state=drain && gres=gpu", &idle));
+        assert!(matches("gres=gpu:a100", &idle));
+        assert!(!matches("gres=gpu:h100 || gres=gpu:a10", &idle));
+        assert!(matches("(cpu_free < 32 || mem_free<100G) && name=~'^node0[0-9]$'", &drained));
+        assert!(matches("feature!=avx512", &drained));
+        assert!(matches("reason != 'bad gpu'", &drained));
+        assert!(!matches("idle_for > 1d", &drained));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expression in [
+            "",
+            "state",
+            "state=",
+            "bogus=1",
+            "cpu_free>=lots",
+            "mem_free>=12X",
+            "state>idle",
+            "(state=idle",
+            "state=idle &",
+            "state=idle extra",
+            "name=~'('",
+            "name='node",
+        ] {
+            assert!(expression.parse::<NodeFilter>().is_err(), "expression: {}", expression);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! "Where can my job run?": checks a resource request against every partition's limits and
+//! the free resources of its nodes.
+
+use serde::Serialize;
+
+use crate::duration::SlurmDuration;
+use crate::gres::Gres;
+use crate::hostlist;
+use crate::memory::{DisplayUnits, Memory};
+use crate::node::{Node, NodeMap};
+use crate::partition::{Partition, PartitionMap};
+
+/// What a job asks for. CPUs, memory and GRES are per node, like `sbatch --mem`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRequest {
+    pub cpus: u32,
+    /// `None` uses the partition's `DefMemPerCPU`, if any.
+    pub mem: Option<Memory>,
+    pub gres: Vec<Gres>,
+    /// `None` uses the partition's default time limit, which always fits.
+    pub time: Option<SlurmDuration>,
+    pub nodes: u32,
+    pub account: Option<String>,
+    pub qos: Option<String>,
+}
+
+impl Default for JobRequest {
+    fn default() -> Self {
+        Self { cpus: 1, mem: None, gres: Vec::new(), time: None, nodes: 1, account: None, qos: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FitStatus {
+    /// Enough nodes have the requested resources free right now.
+    Now,
+    /// Enough healthy nodes are big enough, but some of their resources are in use.
+    Eventually,
+    /// A partition limit or the node hardware rules the request out.
+    Rejected,
+}
+
+/// The verdict for one partition.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionFit {
+    pub partition: String,
+    pub status: FitStatus,
+    /// Nodes that could start the job now, as a compressed hostlist.
+    pub nodes_now: String,
+    /// Healthy nodes big enough for the job, free or not.
+    pub nodes_eventually: String,
+    /// Why the job cannot run (now or at all).
+    pub reasons: Vec<String>,
+}
+
+/// Checks `request` against every partition, in partition order.
+pub fn fit(request: &JobRequest, partitions: &PartitionMap, nodes: &NodeMap, units: DisplayUnits) -> Vec<PartitionFit> {
+    partitions
+        .partitions
+        .values()
+        .map(|partition| fit_partition(request, partition, nodes, units))
+        .collect()
+}
+
+fn fit_partition(request: &JobRequest, partition: &Partition, node_map: &NodeMap, units: DisplayUnits) -> PartitionFit {
+    let mem = request
+        .mem
+        .or_else(|| partition.def_mem_per_cpu.map(|per_cpu| per_cpu * u64::from(request.cpus)));
+    let mut reasons = partition_limits(request, mem, partition, units);
+
+    let nodes: Vec<&Node> = partition.nodes.iter().filter_map(|name| node_map.get(name)).collect();
+    let big_enough: Vec<&Node> = nodes
+        .iter()
+        .copied()
+        .filter(|node| is_healthy(node) && node_fits(request, mem, node, Capacity::Configured))
+        .collect();
+    let free_now: Vec<&Node> = big_enough
+        .iter()
+        .copied()
+        .filter(|node| {
+            node.state.as_ref().is_some_and(|s| s.is_available())
+                && node_fits(request, mem, node, Capacity::Free)
+        })
+        .collect();
+
+    let wanted = request.nodes as usize;
+    let state = partition.state.as_deref().unwrap_or("UP");
+    if matches!(state, "INACTIVE" | "DRAIN") {
+        reasons.push(format!("Partition state is {}", state));
+    }
+    if big_enough.len() < wanted {
+        reasons.push(format!(
+            "Only {} of {} nodes are healthy and have {}",
+            big_enough.len(),
+            nodes.len(),
+            describe(request, mem, units)
+        ));
+    }
+
+    let status = if !reasons.is_empty() {
+        FitStatus::Rejected
+    } else if state != "UP" {
+        // A DOWN partition keeps its queue and starts jobs once it is back up.
+        reasons.push(format!("Partition state is {}", state));
+        FitStatus::Eventually
+    } else if free_now.len() < wanted {
+        reasons.push(format!("{} of {} needed nodes are free now", free_now.len(), wanted));
+        FitStatus::Eventually
+    } else {
+        FitStatus::Now
+    };
+
+    let names = |nodes: &[&Node]| hostlist::compress(&nodes.iter().map(|n| n.name.as_str()).collect::<Vec<_>>());
+    PartitionFit {
+        partition: partition.name.clone(),
+        status,
+        nodes_now: names(&free_now),
+        nodes_eventually: names(&big_enough),
+        reasons,
+    }
+}
+
+/// Partition limits that rule the request out regardless of node state; `mem` is the
+/// requested memory per node, `DefMemPerCPU` already applied.
+fn partition_limits(request: &JobRequest, mem: Option<Memory>, partition: &Partition, units: DisplayUnits) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    if let (Some(time), Some(max_time)) = (request.time, partition.max_time) {
+        if time > max_time {
+            reasons.push(format!("Time limit {} exceeds MaxTime {}", time, max_time));
+        }
+    }
+    if let Some(max_nodes) = partition.max_nodes {
+        if request.nodes > max_nodes {
+            reasons.push(format!("{} nodes exceed MaxNodes {}", request.nodes, max_nodes));
+        }
+    }
+    if let Some(min_nodes) = partition.min_nodes {
+        if request.nodes < min_nodes {
+            reasons.push(format!("{} nodes are fewer than MinNodes {}", request.nodes, min_nodes));
+        }
+    }
+    if let (Some(mem), Some(max_mem)) = (mem, partition.max_mem_per_node) {
+        if mem > max_mem {
+            reasons.push(format!(
+                "{} per node exceeds MaxMemPerNode {}",
+                units.format(mem),
+                units.format(max_mem)
+            ));
+        }
+    }
+    let max_cpus = partition.max_cpus_per_node.as_deref().and_then(|value| value.parse::<u32>().ok());
+    if let Some(max_cpus) = max_cpus {
+        if request.cpus > max_cpus {
+            reasons.push(format!("{} CPUs per node exceed MaxCPUsPerNode {}", request.cpus, max_cpus));
+        }
+    }
+    if let Some(account) = &request.account {
+        if !allows(&partition.allow_accounts, account) {
+            reasons.push(format!("Account {} is not in AllowAccounts", account));
+        }
+    }
+    if let (Some(qos), Some(allow_qos)) = (&request.qos, &partition.allow_qos) {
+        let allowed: Vec<String> = allow_qos.split(',').map(String::from).collect();
+        if !allows(&allowed, qos) {
+            reasons.push(format!("QOS {} is not in AllowQos", qos));
+        }
+    }
+
+    reasons
+}
+
+/// Slurm access lists: empty or `ALL` allow everyone.
+fn allows(list: &[String], name: &str) -> bool {
+    list.is_empty() || list.iter().any(|entry| entry == "ALL" || entry == name)
+}
+
+/// Nodes that will eventually take jobs: responding, not down and not draining.
+fn is_healthy(node: &Node) -> bool {
+    node.state
+        .as_ref()
+        .is_some_and(|state| !state.is_down() && !state.is_drain() && state.is_responding())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Capacity {
+    Configured,
+    Free,
+}
+
+fn node_fits(request: &JobRequest, mem: Option<Memory>, node: &Node, capacity: Capacity) -> bool {
+    let (cpus, memory, gres) = match capacity {
+        Capacity::Configured => (node.cpu_total, node.real_memory, node.gres.clone()),
+        Capacity::Free => (node.cpu_free(), node.free_memory(), node.free_gres()),
+    };
+
+    cpus.unwrap_or(0) >= request.cpus
+        && mem.is_none_or(|mem| memory.is_some_and(|memory| memory >= mem))
+        && request.gres.iter().all(|wanted| gres_count(&gres, wanted) >= wanted.count)
+}
+
+/// How many of `wanted` a GRES list provides; an untyped request (`gpu:2`) counts every type.
+fn gres_count(gres: &[Gres], wanted: &Gres) -> u64 {
+    gres.iter()
+        .filter(|g| g.name == wanted.name && (wanted.kind.is_none() || g.kind == wanted.kind))
+        .map(|g| g.count)
+        .sum()
+}
+
+/// `32 CPUs, 200 GiB, gpu:a100:2`
+fn describe(request: &JobRequest, mem: Option<Memory>, units: DisplayUnits) -> String {
+    let mut parts = vec![format!("{} CPUs", request.cpus)];
+    parts.extend(mem.map(|mem| units.format(mem)));
+    parts.extend(request.gres.iter().map(Gres::to_string));
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gres::parse_gres_list;
+    use crate::source::StaticSource;
+
+    const DUMP: &str = "\
+PartitionName=short Nodes=gpu[01-02] MaxTime=1-00:00:00 MaxNodes=2 State=UP
+PartitionName=long Nodes=gpu[01-02] MaxTime=7-00:00:00 AllowAccounts=physics State=UP
+PartitionName=cpu Nodes=node01 MaxTime=UNLIMITED State=UP
+PartitionName=bigmem Nodes=node01 DefMemPerCPU=8000 MaxMemPerNode=256000 State=UP
+NodeName=gpu01 CPUTot=64 CPUAlloc=48 RealMemory=512000 AllocMem=256000 Gres=gpu:a100:4 GresUsed=gpu:a100:3(IDX:0-2) State=MIXED
+NodeName=gpu02 CPUTot=64 CPUAlloc=0 RealMemory=512000 AllocMem=0 Gres=gpu:a100:4 GresUsed=gpu:a100:0(IDX:N/A) State=IDLE
+NodeName=node01 CPUTot=32 CPUAlloc=0 RealMemory=256000 AllocMem=0 State=IDLE
+";
+
+    fn check(request: &JobRequest) -> Vec<PartitionFit> {
+        let source = StaticSource::new(DUMP);
+        let nodes = NodeMap::build(&source).unwrap();
+        let partitions = PartitionMap::build(&source).unwrap();
+        fit(request, &partitions, &nodes, DisplayUnits::default())
+    }
+
+    fn status<'a>(fits: &'a [PartitionFit], partition: &str) -> &'a PartitionFit {
+        fits.iter().find(|fit| fit.partition == partition).unwrap()
+    }
+
+    #[test]
+    fn test_fit() {
+        let request = JobRequest {
+            cpus: 32,
+            mem: Some("200G".parse().unwrap()),
+            gres: parse_gres_list("gpu:a100:2").unwrap(),
+            time: Some("2-00:00:00".parse().unwrap()),
+            nodes: 1,
+            account: Some("chemistry".to_string()),
+            ..JobRequest::default()
+        };
+        let fits = check(&request);
+
+        let short = status(&fits, "short");
+        assert_eq!(short.status, FitStatus::Rejected);
+        assert_eq!(short.reasons, vec!["Time limit 2d exceeds MaxTime 1d"]);
+
+        let long = status(&fits, "long");
+        assert_eq!(long.status, FitStatus::Rejected);
+        assert_eq!(long.reasons, vec!["Account chemistry is not in AllowAccounts"]);
+
+        let cpu = status(&fits, "cpu");
+        assert_eq!(cpu.status, FitStatus::Rejected);
+        assert_eq!(cpu.reasons, vec!["Only 0 of 1 nodes are healthy and have 32 CPUs, 200 GiB, gpu:a100:2"]);
+
+        let request = JobRequest { account: Some("physics".to_string()), nodes: 2, ..request };
+        let long = status(&check(&request), "long").clone();
+        assert_eq!(long.status, FitStatus::Eventually);
+        assert_eq!(long.nodes_now, "gpu02");
+        assert_eq!(long.nodes_eventually, "gpu[01-02]");
+
+        let request = JobRequest { nodes: 1, ..request };
+        assert_eq!(status(&check(&request), "long").status, FitStatus::Now);
+    }
+
+    #[test]
+    fn test_fit_default_memory_limit() {
+        let request = JobRequest { cpus: 16, nodes: 1, ..JobRequest::default() };
+        assert_eq!(status(&check(&request), "bigmem").status, FitStatus::Now);
+
+        // 64 CPUs at DefMemPerCPU=8000 need 512000 MB, above MaxMemPerNode
+        let bigmem = status(&check(&JobRequest { cpus: 64, ..request }), "bigmem").clone();
+        assert_eq!(bigmem.status, FitStatus::Rejected);
+        assert_eq!(bigmem.reasons[0], "500 GiB per node exceeds MaxMemPerNode 250 GiB");
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::hostlist;
+use crate::node::Node;
+
+/// One generic resource entry of a node's `Gres=` or `GresUsed=` field, e.g.
+/// `gpu:a100:4(S:0-1)` or `gpu:a100:2(IDX:0-1)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Gres {
+    pub name: String,
+    /// The GRES type, e.g. `a100`; `None` for untyped entries such as `shard:8`.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub count: u64,
+    /// Sockets the resource is bound to (`S:0-1`), if given.
+    pub sockets: Vec<u32>,
+    /// Device indices in use (`IDX:0,2`), only present in `GresUsed`.
+    pub indices: Vec<u32>,
+}
+
+impl Gres {
+    pub fn new(name: &str, kind: Option<&str>, count: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: kind.map(String::from),
+            count,
+            ..Self::default()
+        }
+    }
+
+    /// Whether `self` describes the same resource as `other`, ignoring counts and bindings.
+    pub fn same_resource(&self, other: &Gres) -> bool {
+        self.name == other.name && self.kind == other.kind
+    }
+
+    /// The GRES type, or `-` for untyped entries.
+    pub fn kind_or_dash(&self) -> &str {
+        self.kind.as_deref().unwrap_or("-")
+    }
+}
+
+/// Parses a `Gres=`/`GresUsed=` value into its entries. `(null)` and empty values yield none.
+pub fn parse_gres_list(value: &str) -> Result<Vec<Gres>, String> {
+    let value = value.trim();
+    if value.is_empty() || value == "(null)" {
+        return Ok(Vec::new());
+    }
+
+    // Commas also appear inside the `(S:0,2)` suffix, so only split outside parentheses.
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                entries.push(value[start..i].parse()?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(value[start..].parse()?);
+
+    Ok(entries)
+}
+
+impl FromStr for Gres {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let unclosed = || format!("Unclosed '(' in GRES '{}'", value);
+
+        // Peel the trailing `(S:0-1)`/`(IDX:0-1)` groups off; an untyped `GresUsed` entry
+        // prints its type as `(null)`, as in `gpu:(null):2(IDX:0-1)`.
+        let mut spec = value;
+        let mut bindings = Vec::new();
+        while let Some(rest) = spec.strip_suffix(')') {
+            let open = rest.rfind('(').ok_or_else(unclosed)?;
+            if rest[..open].ends_with(':') {
+                break;
+            }
+            bindings.push(&rest[open + 1..]);
+            spec = &rest[..open];
+        }
+
+        let parts: Vec<&str> = spec.split(':').collect();
+        if parts.iter().any(|part| part.contains('(') && *part != "(null)") {
+            return Err(unclosed());
+        }
+        let kind = |kind: &str| Some(kind.to_string()).filter(|kind| kind != "(null)");
+        let mut gres = match parts.as_slice() {
+            [name] => Gres::new(name, None, 1),
+            [name, count] if parse_count(count, value).is_ok() => {
+                Gres::new(name, None, parse_count(count, value)?)
+            }
+            [name, kind_name] => Gres { kind: kind(kind_name), ..Gres::new(name, None, 1) },
+            [name, kind_name, count] => {
+                Gres { kind: kind(kind_name), ..Gres::new(name, None, parse_count(count, value)?) }
+            }
+            _ => return Err(format!("Invalid GRES '{}'", value)),
+        };
+        if gres.name.is_empty() {
+            return Err(format!("GRES '{}' has no name", value));
+        }
+
+        for binding in bindings {
+            match binding.split_once(':') {
+                Some(("S", sockets)) => gres.sockets = parse_indices(sockets, value)?,
+                Some(("IDX", indices)) => gres.indices = parse_indices(indices, value)?,
+                _ => {} // e.g. `(no_consume)`
+            }
+        }
+
+        Ok(gres)
+    }
+}
+
+impl fmt::Display for Gres {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            Some(kind) => write!(f, "{}:{}:{}", self.name, kind, self.count),
+            None => write!(f, "{}:{}", self.name, self.count),
+        }
+    }
+}
+
+/// Parses a GRES count, which may carry a `K`/`M`/`G` multiplier (e.g. `mps:100K`).
+fn parse_count(count: &str, gres: &str) -> Result<u64, String> {
+    let (number, multiplier) = match count.chars().last() {
+        Some('K' | 'k') => (&count[..count.len() - 1], 1024),
+        Some('M' | 'm') => (&count[..count.len() - 1], 1024 * 1024),
+        Some('G' | 'g') => (&count[..count.len() - 1], 1024 * 1024 * 1024),
+        _ => (count, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid count in GRES '{}'", gres))
+}
+
+/// Parses socket or index lists such as `0-1` or `0,2-3`; `N/A` means none.
+fn parse_indices(value: &str, gres: &str) -> Result<Vec<u32>, String> {
+    if value == "N/A" || value.is_empty() {
+        return Ok(Vec::new());
+    }
+    hostlist::expand(&format!("[{}]", value))
+        .map_err(|e| format!("Invalid index list in GRES '{}': {}", gres, e))?
+        .iter()
+        .map(|i| i.parse().map_err(|_| format!("Invalid index list in GRES '{}'", gres)))
+        .collect()
+}
+
+/// Configured GRES of `node` minus what `GresUsed` reports; never negative.
+pub fn free_gres(node: &Node) -> Vec<Gres> {
+    node.gres
+        .iter()
+        .map(|configured| {
+            let used = used_count(node, configured);
+            Gres {
+                count: configured.count.saturating_sub(used),
+                ..configured.clone()
+            }
+        })
+        .collect()
+}
+
+fn used_count(node: &Node, configured: &Gres) -> u64 {
+    node.gres_used
+        .iter()
+        .filter(|used| used.same_resource(configured))
+        .map(|used| used.count)
+        .sum()
+}
+
+/// Totals of one GRES type across a set of nodes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GresTotals {
+    pub nodes: u64,
+    pub total: u64,
+    pub used: u64,
+    /// Unused on nodes that accept jobs.
+    pub free: u64,
+    /// Unused but on nodes that are down, drained or otherwise not accepting jobs.
+    pub unavailable: u64,
+}
+
+/// Sums the `name` GRES (e.g. `gpu`) of `nodes` per type; untyped entries use the key `-`.
+pub fn inventory<'a>(nodes: impl IntoIterator<Item = &'a Node>, name: &str) -> BTreeMap<String, GresTotals> {
+    let mut totals: BTreeMap<String, GresTotals> = BTreeMap::new();
+
+    for node in nodes {
+        let available = node.state.as_ref().is_some_and(|s| s.is_available());
+        for configured in node.gres.iter().filter(|g| g.name == name) {
+            let used = used_count(node, configured).min(configured.count);
+            let entry = totals.entry(configured.kind_or_dash().to_string()).or_default();
+            entry.nodes += 1;
+            entry.total += configured.count;
+            entry.used += used;
+            if available {
+                entry.free += configured.count - used;
+            } else {
+                entry.unavailable += configured.count - used;
+            }
+        }
+    }
+
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gres_list() {
+        let gres = parse_gres_list("gpu:a100:4(S:0-1),shard:8,gpu:2").unwrap();
+
+        assert_eq!(gres.len(), 3);
+        assert_eq!(gres[0].name, "gpu");
+        assert_eq!(gres[0].kind.as_deref(), Some("a100"));
+        assert_eq!(gres[0].count, 4);
+        assert_eq!(gres[0].sockets, vec![0, 1]);
+        assert_eq!(gres[1], Gres::new("shard", None, 8));
+        assert_eq!(gres[2], Gres::new("gpu", None, 2));
+
+        let used = parse_gres_list("gpu:a100:2(IDX:0,2-3),mps:100K(IDX:N/A)").unwrap();
+        assert_eq!(used[0].indices, vec![0, 2, 3]);
+        assert_eq!(used[1].count, 100 * 1024);
+
+        assert_eq!(parse_gres_list("gpu:tesla(S:0,2)").unwrap()[0].sockets, vec![0, 2]);
+        assert_eq!(parse_gres_list("gpu:1g.5gb").unwrap()[0].kind.as_deref(), Some("1g.5gb"));
+        assert!(parse_gres_list("(null)").unwrap().is_empty());
+        assert!(parse_gres_list("gpu:a100:x").is_err());
+        assert!(parse_gres_list("gpu:a100:4(S:0").is_err());
+        assert!(parse_gres_list("gpu:a100:99999999999999999G").is_err());
+
+        let untyped = parse_gres_list("gpu:(null):2(IDX:0-1)").unwrap();
+        assert_eq!(untyped[0], Gres { indices: vec![0, 1], ..Gres::new("gpu", None, 2) });
+        assert_eq!(parse_gres_list("gpu:(null)").unwrap()[0], Gres::new("gpu", None, 1));
+    }
+
+    #[test]
+    fn test_free_gres_untyped_used() {
+        let stdout = "NodeName=gpu03 CPUTot=32 CPUAlloc=16 RealMemory=256000 AllocMem=64000 \
+            Gres=gpu:4(S:0-1) GresUsed=gpu:(null):2(IDX:0-1) State=MIXED\n";
+        let node = &Node::parse_nodes(stdout).unwrap()[0];
+
+        assert_eq!(free_gres(node), vec![Gres { sockets: vec![0, 1], ..Gres::new("gpu", None, 2) }]);
+        assert_eq!(inventory([node], "gpu")["-"].used, 2);
+    }
+
+    #[test]
+    fn test_free_gres_and_inventory() {
+        let busy = Node {
+            gres: parse_gres_list("gpu:a100:4(S:0-1)").unwrap(),
+            gres_used: parse_gres_list("gpu:a100:3(IDX:0-2)").unwrap(),
+            state: "MIXED".parse().ok(),
+            ..Node::default()
+        };
+        let drained = Node {
+            gres: parse_gres_list("gpu:a100:4,gpu:v100:2").unwrap(),
+            state: "IDLE+DRAIN".parse().ok(),
+            ..Node::default()
+        };
+
+        assert_eq!(free_gres(&busy)[0].count, 1);
+
+        let totals = inventory([&busy, &drained], "gpu");
+        assert_eq!(
+            totals["a100"],
+            GresTotals { nodes: 2, total: 8, used: 3, free: 1, unavailable: 4 }
+        );
+        assert_eq!(totals["v100"].unavailable, 2);
+    }
+}
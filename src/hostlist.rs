@@ -0,0 +1,369 @@
+use std::cmp::Ordering;
+
+use crate::error::SlurmToolError;
+
+/// Upper bound on the number of names a single hostlist may expand to. Protects against
+/// typos such as `node[1-999999999]` allocating gigabytes of strings.
+const MAX_HOSTS: usize = 1 << 20;
+
+/// A piece of a single host pattern: either literal text or the alternatives of a bracket group.
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Group(Vec<String>),
+}
+
+/// Expands a Slurm hostlist expression into individual host names.
+///
+/// Supports everything Slurm emits in `Nodes=` style fields:
+/// - comma separated hosts: `login1,node5`
+/// - ranges and lists inside brackets: `node[1-3,7]`
+/// - zero padding, taken from the width of the lower bound: `node[001-003]`
+/// - several bracket groups per host, expanded left to right: `rack[1-2]-node[01-04]`
+/// - text after a bracket group: `gpu[1-4]-ib`
+/// - stepped ranges: `node[1-9:2]`
+///
+/// `(null)` and the empty string expand to no hosts.
+///
+/// ```
+/// use slurmtool::hostlist::expand;
+///
+/// assert_eq!(expand("node[08-10],login").unwrap(), vec!["node08", "node09", "node10", "login"]);
+/// ```
+pub fn expand(hostlist: &str) -> Result<Vec<String>, SlurmToolError> {
+    let hostlist = hostlist.trim();
+    if hostlist.is_empty() || hostlist == "(null)" {
+        return Ok(Vec::new());
+    }
+
+    let mut hosts = Vec::new();
+    for pattern in split_top_level(hostlist)? {
+        if pattern.is_empty() {
+            return Err(SlurmToolError::Hostlist(format!("Empty host name in hostlist '{}'", hostlist)));
+        }
+        expand_pattern(pattern, &mut hosts)?;
+        if hosts.len() > MAX_HOSTS {
+            return Err(SlurmToolError::Hostlist(format!("Hostlist '{}' expands to more than {} hosts", hostlist, MAX_HOSTS)));
+        }
+    }
+
+    Ok(hosts)
+}
+
+/// Splits a hostlist on the commas that are not inside a bracket group.
+fn split_top_level(hostlist: &str) -> Result<Vec<&str>, SlurmToolError> {
+    let mut patterns = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in hostlist.char_indices() {
+        match c {
+            '[' => {
+                if depth > 0 {
+                    return Err(SlurmToolError::Hostlist(format!("Nested '[' in hostlist '{}'", hostlist)));
+                }
+                depth += 1;
+            }
+            ']' => {
+                if depth == 0 {
+                    return Err(SlurmToolError::Hostlist(format!("Unmatched ']' in hostlist '{}'", hostlist)));
+                }
+                depth -= 1;
+            }
+            ',' if depth == 0 => {
+                patterns.push(hostlist[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if depth != 0 {
+        return Err(SlurmToolError::Hostlist(format!("Unmatched '[' in hostlist '{}'", hostlist)));
+    }
+    patterns.push(hostlist[start..].trim());
+
+    Ok(patterns)
+}
+
+/// Expands one host pattern (no top-level commas) and appends the names to `hosts`.
+fn expand_pattern(pattern: &str, hosts: &mut Vec<String>) -> Result<(), SlurmToolError> {
+    let mut segments = Vec::new();
+    let mut rest = pattern;
+
+    while let Some(open) = rest.find('[') {
+        // `split_top_level` already checked that brackets are balanced and not nested.
+        let close = open + rest[open..].find(']').unwrap();
+        if open > 0 {
+            segments.push(Segment::Literal(rest[..open].to_string()));
+        }
+        segments.push(Segment::Group(expand_group(&rest[open + 1..close])?));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+
+    // Cartesian product; the leftmost group varies slowest, matching Slurm's ordering.
+    let mut names = vec![String::new()];
+    for segment in &segments {
+        match segment {
+            Segment::Literal(text) => {
+                for name in &mut names {
+                    name.push_str(text);
+                }
+            }
+            Segment::Group(alternatives) => {
+                if names.len().saturating_mul(alternatives.len()) > MAX_HOSTS {
+                    return Err(SlurmToolError::Hostlist(format!("Hostlist '{}' expands to more than {} hosts", pattern, MAX_HOSTS)));
+                }
+                names = names
+                    .iter()
+                    .flat_map(|name| alternatives.iter().map(move |alt| format!("{}{}", name, alt)))
+                    .collect();
+            }
+        }
+    }
+
+    hosts.extend(names);
+    Ok(())
+}
+
+/// Expands the contents of a bracket group, e.g. `01-03,7,10-20:5`.
+fn expand_group(group: &str) -> Result<Vec<String>, SlurmToolError> {
+    let mut values = Vec::new();
+
+    for part in group.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            return Err(SlurmToolError::Hostlist(format!("Empty range in '[{}]'", group)));
+        }
+
+        let (range, step) = match part.split_once(':') {
+            Some((range, step)) => {
+                let step: u64 = parse_number(step, group)?;
+                if step == 0 {
+                    return Err(SlurmToolError::Hostlist(format!("Step must be positive in '[{}]'", group)));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start_str, end_str) = match range.split_once('-') {
+            Some((start, end)) => (start, end),
+            None => (range, range),
+        };
+        let start = parse_number(start_str, group)?;
+        let end = parse_number(end_str, group)?;
+        if start > end {
+            return Err(SlurmToolError::Hostlist(format!("Range '{}' is decreasing in '[{}]'", range, group)));
+        }
+        if (end - start) / step >= MAX_HOSTS as u64 {
+            return Err(SlurmToolError::Hostlist(format!("Range '{}' expands to more than {} hosts", range, MAX_HOSTS)));
+        }
+
+        let width = start_str.len();
+        let mut i = start;
+        while i <= end {
+            values.push(format!("{:0width$}", i, width = width));
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+fn parse_number(value: &str, group: &str) -> Result<u64, SlurmToolError> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(SlurmToolError::Hostlist(format!("'{}' is not a number in '[{}]'", value, group)));
+    }
+    value
+        .parse()
+        .map_err(|_| SlurmToolError::Hostlist(format!("'{}' is out of range in '[{}]'", value, group)))
+}
+
+/// Compresses host names into a Slurm hostlist expression; the inverse of [`expand`].
+///
+/// Names are sorted in natural order and de-duplicated, then hosts that only differ in their
+/// last run of digits are folded into one bracket group. Zero padding is preserved, so
+/// `expand(&compress(&hosts))` returns the sorted hosts unchanged.
+///
+/// ```
+/// use slurmtool::hostlist::compress;
+///
+/// let hosts = ["node02", "node01", "node03", "node15", "login"];
+/// assert_eq!(compress(&hosts), "login,node[01-03,15]");
+/// ```
+pub fn compress<S: AsRef<str>>(hosts: &[S]) -> String {
+    let mut names: Vec<&str> = hosts.iter().map(|h| h.as_ref()).filter(|h| !h.is_empty()).collect();
+    names.sort_by(|a, b| natord::compare(a, b));
+    names.dedup();
+
+    // Group by everything except the last run of digits, keeping first-seen order.
+    let mut groups: Vec<(&str, &str, Vec<&str>)> = Vec::new();
+    let mut plain: Vec<(usize, &str)> = Vec::new();
+    for name in names {
+        match split_last_number(name) {
+            Some((prefix, digits, suffix)) => {
+                match groups.iter_mut().find(|(p, s, _)| *p == prefix && *s == suffix) {
+                    Some((_, _, numbers)) => numbers.push(digits),
+                    None => groups.push((prefix, suffix, vec![digits])),
+                }
+            }
+            None => plain.push((groups.len(), name)),
+        }
+    }
+
+    let mut parts = Vec::new();
+    let mut plain = plain.into_iter().peekable();
+    for (i, (prefix, suffix, mut numbers)) in groups.into_iter().enumerate() {
+        while let Some((_, name)) = plain.next_if(|(pos, _)| *pos <= i) {
+            parts.push(name.to_string());
+        }
+        numbers.sort_by(|a, b| compare_numbers(a, b));
+        let ranges = compress_numbers(&numbers);
+        if numbers.len() == 1 {
+            parts.push(format!("{}{}{}", prefix, numbers[0], suffix));
+        } else {
+            parts.push(format!("{}[{}]{}", prefix, ranges.join(","), suffix));
+        }
+    }
+    parts.extend(plain.map(|(_, name)| name.to_string()));
+
+    parts.join(",")
+}
+
+/// Splits `name` around its last run of ASCII digits: `gpu12-ib` -> (`gpu`, `12`, `-ib`).
+fn split_last_number(name: &str) -> Option<(&str, &str, &str)> {
+    let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = name[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    Some((&name[..start], &name[start..end], &name[end..]))
+}
+
+/// Orders digit strings by value, then by width so `1` sorts before `01`.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+/// Folds sorted digit strings into `start-end` ranges that expand back to the same strings.
+fn compress_numbers(numbers: &[&str]) -> Vec<String> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+
+    while i < numbers.len() {
+        let start = numbers[i];
+        let width = start.len();
+        let mut end = i;
+        while end + 1 < numbers.len() && is_successor(numbers[end], numbers[end + 1], width) {
+            end += 1;
+        }
+        if end == i {
+            ranges.push(start.to_string());
+        } else {
+            ranges.push(format!("{}-{}", start, numbers[end]));
+        }
+        i = end + 1;
+    }
+
+    ranges
+}
+
+/// Whether `next` is `current + 1` printed with the range's zero padding.
+fn is_successor(current: &str, next: &str, width: usize) -> bool {
+    match current.parse::<u64>() {
+        Ok(value) => format!("{:0width$}", value + 1, width = width) == next,
+        // Too large to be a range bound; keep it as a separate entry.
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let test_cases = vec![
+            ("node[001-003]", vec!["node001", "node002", "node003"]),
+            ("node[8-10]", vec!["node8", "node9", "node10"]),
+            ("node[08-10]", vec!["node08", "node09", "node10"]),
+            (
+                "rack[1-2]-node[01-02]",
+                vec!["rack1-node01", "rack1-node02", "rack2-node01", "rack2-node02"],
+            ),
+            ("gpu[1-2]-ib,login", vec!["gpu1-ib", "gpu2-ib", "login"]),
+            ("node[1-9:4,20]", vec!["node1", "node5", "node9", "node20"]),
+            ("a[1-2],b[3]", vec!["a1", "a2", "b3"]),
+            (
+                "node[18446744073709551614-18446744073709551615]",
+                vec!["node18446744073709551614", "node18446744073709551615"],
+            ),
+            ("(null)", vec![]),
+            ("", vec![]),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(expand(input).unwrap(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        for input in [
+            "node[1-3",
+            "node1-3]",
+            "node[a-c]",
+            "node[3-1]",
+            "node[1-4:0]",
+            "node[]",
+            "node[[1]]",
+            "node1,,node2",
+            "node[1-99999999999]",
+        ] {
+            assert!(expand(input).is_err(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_compress() {
+        let test_cases = vec![
+            (vec!["node3", "node1", "node2", "node5"], "node[1-3,5]"),
+            (vec!["node08", "node09", "node10"], "node[08-10]"),
+            (vec!["node8", "node9", "node10"], "node[8-10]"),
+            (vec!["node1", "node01"], "node[1,01]"),
+            (vec!["gpu1-ib", "gpu2-ib", "gpu4-ib"], "gpu[1-2,4]-ib"),
+            (vec!["login", "node5"], "login,node5"),
+            (vec!["node2", "node2", "node1"], "node[1-2]"),
+            (vec![], ""),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(compress(&input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_compress_round_trip() {
+        for hostlist in [
+            "node[001-120,200]",
+            "rack[1-2]-node[01-04]",
+            "gpu[1-4]-ib,login[1-2]",
+            "a[9-11],b[099-101]",
+        ] {
+            let hosts = expand(hostlist).unwrap();
+            assert_eq!(expand(&compress(&hosts)).unwrap(), hosts, "hostlist: {}", hostlist);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::gres::Gres;
+use crate::hostlist;
+use crate::job_state::JobState;
+use crate::json;
+use crate::memory::Memory;
+use crate::oneliner::{self, not_null};
+use crate::source::{Entity, SlurmSource};
+use crate::table::{Cell, Value};
+use crate::timestamp::{self, parse_timestamp};
+use crate::tres::Tres;
+
+/// Columns `Job::value` knows, for the `jobs` table.
+pub const JOB_COLUMNS: &[&str] = &[
+    "job_id", "name", "user", "account", "partition", "qos", "state", "reason", "time",
+    "time_limit", "nodes", "cpus", "mem", "gres", "nodelist", "submit_time", "start_time",
+    "end_time",
+];
+
+/// Every key `scontrol show job --oneliner` may print, including the ones `Job` ignores.
+/// See `node::NODE_KEYS` for why the list has to be complete.
+pub const JOB_KEYS: &[&str] = &[
+    "JobId", "ArrayJobId", "ArrayTaskId", "ArrayTaskThrottle", "HetJobId", "HetJobOffset",
+    "HetJobIdSet", "JobName", "UserId", "GroupId", "MCS_label", "Priority", "Nice", "Account",
+    "QOS", "JobState", "Reason", "Dependency", "Requeue", "Restarts", "BatchFlag", "Reboot",
+    "ExitCode", "DerivedExitCode", "RunTime", "TimeLimit", "TimeMin", "SubmitTime",
+    "EligibleTime", "AccrueTime", "StartTime", "EndTime", "Deadline", "SuspendTime",
+    "SecsPreSuspend", "LastSchedEval", "Scheduler", "Partition", "AllocNode:Sid",
+    "ReqNodeList", "ExcNodeList", "NodeList", "BatchHost", "NumNodes", "NumCPUs", "NumTasks",
+    "CPUs/Task", "ReqB:S:C:T", "TRES", "ReqTRES", "AllocTRES", "Socks/Node",
+    "NtasksPerN:B:S:C", "CoreSpec", "MinCPUsNode", "MinMemoryNode", "MinMemoryCPU",
+    "MinTmpDiskNode", "Features", "DelayBoot", "OverSubscribe", "Contiguous", "Licenses",
+    "Network", "Command", "WorkDir", "StdErr", "StdIn", "StdOut", "Power", "TresPerNode",
+    "TresPerJob", "TresPerTask", "TresPerSocket", "CpusPerTres", "MemPerTres", "MailUser",
+    "MailType", "Comment", "AdminComment", "SystemComment", "Reservation",
+    "PreemptEligibleTime", "PreemptTime", "ResizeTime", "SiblingsActive", "SiblingsViable",
+    "ClusterFeatures", "Container", "ContainerID", "NtasksPerTRES", "OOMKillStep",
+    "KillOInInvalidDependent", "Prefer", "Clusters", "ResvPorts", "Extra", "SegmentSize",
+];
+
+#[derive(Debug, Default)]
+pub struct JobMap {
+    pub jobs: BTreeMap<u64, Job>,
+}
+
+impl JobMap {
+    pub fn build(source: &dyn SlurmSource) -> Result<Self, SlurmToolError> {
+        let jobs = Job::fetch_and_parse_jobs(source)?;
+        let mut job_map = JobMap::default();
+
+        for job in jobs {
+            // make sure the job id is unique
+            if job_map.jobs.contains_key(&job.job_id) {
+                return Err(SlurmToolError::Duplicate { kind: "job", name: job.job_id.to_string() });
+            }
+
+            job_map.jobs.insert(job.job_id, job);
+        }
+
+        Ok(job_map)
+    }
+
+    pub fn get(&self, job_id: u64) -> Option<&Job> {
+        self.jobs.get(&job_id)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Job {
+    pub job_id: u64,
+    pub array_job_id: Option<u64>,
+    /// A task index, or the remaining tasks (`3-10%2`) of a pending array.
+    pub array_task_id: Option<String>,
+    pub name: Option<String>,
+    pub user: Option<String>,
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    pub qos: Option<String>,
+    pub state: Option<JobState>,
+    /// Why the job is pending or was held; `None` for scontrol's `Reason=None`.
+    pub reason: Option<String>,
+    pub req_tres: Option<Tres>,
+    pub alloc_tres: Option<Tres>,
+    /// The allocated nodes, expanded.
+    pub nodes: Vec<String>,
+    pub num_nodes: Option<u32>,
+    pub num_cpus: Option<u32>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub submit_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub end_time: Option<DateTime<Local>>,
+    pub time_limit: Option<SlurmDuration>,
+    pub run_time: Option<SlurmDuration>,
+}
+
+impl Job {
+    pub fn from_fields(fields: &[(&str, &str)]) -> Self {
+        let mut job = Job::default();
+
+        for (key, value) in fields {
+            match *key {
+                "JobId" => job.job_id = value.parse().unwrap_or_default(),
+                "ArrayJobId" => job.array_job_id = value.parse().ok(),
+                "ArrayTaskId" => job.array_task_id = Some(value.to_string()),
+                "JobName" => job.name = Some(value.to_string()),
+                "UserId" => job.user = Some(user_name(value).to_string()),
+                "Account" => job.account = not_null(value),
+                "Partition" => job.partition = not_null(value),
+                "QOS" => job.qos = not_null(value),
+                "JobState" => job.state = value.parse().ok(),
+                "Reason" => job.reason = not_null(value).filter(|reason| reason != "None"),
+                "ReqTRES" => job.req_tres = value.parse().ok(),
+                "AllocTRES" => job.alloc_tres = value.parse().ok().filter(|tres: &Tres| !tres.is_empty()),
+                "NodeList" => {
+                    if let Some(nodes) = not_null(value) {
+                        job.nodes = hostlist::expand(&nodes).unwrap_or_else(|e| {
+                            log::warn!("Ignoring node list of job {}: {}", job.job_id, e);
+                            Vec::new()
+                        });
+                    }
+                }
+                // `NumNodes=2-4` while pending: the minimum is what the job needs.
+                "NumNodes" => job.num_nodes = value.split('-').next().and_then(|n| n.parse().ok()),
+                "NumCPUs" => job.num_cpus = value.split('-').next().and_then(|n| n.parse().ok()),
+                "SubmitTime" => job.submit_time = parse_timestamp(value).ok().flatten(),
+                "StartTime" => job.start_time = parse_timestamp(value).ok().flatten(),
+                "EndTime" => job.end_time = parse_timestamp(value).ok().flatten(),
+                "TimeLimit" => job.time_limit = SlurmDuration::parse_optional(value).ok().flatten(),
+                "RunTime" => job.run_time = SlurmDuration::parse_optional(value).ok().flatten(),
+                _ => {} // Ignore any unknown keys
+            }
+        }
+
+        job
+    }
+
+    /// Fetch the job records from `source` and parse them into a vector of `Job` structs.
+    pub fn fetch_and_parse_jobs(source: &dyn SlurmSource) -> Result<Vec<Self>, SlurmToolError> {
+        let stdout = source.show(Entity::Job)?;
+        Job::parse_jobs(&stdout)
+    }
+
+    /// Parse `scontrol show job` output into a vector of `Job` structs.
+    ///
+    /// Accepts both `--json` documents and `-a --oneliner` records.
+    pub fn parse_jobs(stdout: &str) -> Result<Vec<Self>, SlurmToolError> {
+        if json::is_json(stdout) {
+            return json::parse_jobs(stdout);
+        }
+
+        let mut jobs = Vec::new();
+
+        for (number, line) in stdout.lines().enumerate() {
+            let fields = oneliner::tokenize(line, JOB_KEYS);
+
+            if fields.is_empty() {
+                continue;
+            }
+            oneliner::check_record(&fields, "JobId", number + 1)?;
+
+            jobs.push(Job::from_fields(&fields));
+        }
+
+        Ok(jobs)
+    }
+
+    /// The id as squeue shows it: `1234`, `1234_5` for an array task, or `1234_[6-10]` for
+    /// the pending rest of an array.
+    pub fn id(&self) -> String {
+        match (self.array_job_id, &self.array_task_id) {
+            (Some(array_job_id), Some(task)) if task.chars().all(|c| c.is_ascii_digit()) => {
+                format!("{}_{}", array_job_id, task)
+            }
+            (Some(array_job_id), Some(task)) => format!("{}_[{}]", array_job_id, task),
+            _ => self.job_id.to_string(),
+        }
+    }
+
+    /// The allocated TRES once the job runs, the requested ones before.
+    pub fn tres(&self) -> Option<&Tres> {
+        self.alloc_tres.as_ref().or(self.req_tres.as_ref())
+    }
+
+    pub fn cpus(&self) -> Option<u64> {
+        self.tres().and_then(Tres::cpu).or(self.num_cpus.map(u64::from))
+    }
+
+    pub fn memory(&self) -> Option<Memory> {
+        self.tres().and_then(Tres::mem)
+    }
+
+    /// The GRES of `tres()`, e.g. `gpu:a100:2`.
+    pub fn gres(&self) -> Vec<Gres> {
+        self.tres().map(Tres::gres_list).unwrap_or_default()
+    }
+
+    /// This job's share of each of its nodes.
+    pub fn node_share(&self) -> NodeJob {
+        let nodes = self.num_nodes.map_or(self.nodes.len() as u64, u64::from);
+        NodeJob {
+            job_id: self.id(),
+            user: self.user.clone(),
+            account: self.account.clone(),
+            partition: self.partition.clone(),
+            state: self.state.clone(),
+            tres: self.alloc_tres.as_ref().map(|tres| tres.per_node(nodes)).unwrap_or_default(),
+            run_time: self.run_time,
+            time_limit: self.time_limit,
+        }
+    }
+
+    /// Whether any of the names in `users` is the job owner; an empty list matches every job.
+    pub fn has_user(&self, users: &[String]) -> bool {
+        users.is_empty() || self.user.as_ref().is_some_and(|user| users.contains(user))
+    }
+
+    /// Whether the job is in one of `partitions`. Jobs submitted to several partitions list
+    /// them comma separated until they start.
+    pub fn in_partition(&self, partitions: &[String]) -> bool {
+        partitions.is_empty()
+            || self
+                .partition
+                .as_ref()
+                .is_some_and(|p| p.split(',').any(|p| partitions.iter().any(|wanted| wanted == p)))
+    }
+
+    /// Whether the state matches one of `states` (names or squeue codes).
+    pub fn has_state(&self, states: &[String]) -> bool {
+        states.is_empty()
+            || self
+                .state
+                .as_ref()
+                .is_some_and(|state| states.iter().any(|wanted| state.matches(wanted)))
+    }
+
+    /// The value of one of `JOB_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        let text = |value: Option<&String>| Value::from_option(value.cloned(), Value::Text);
+        let time = |value: Option<DateTime<Local>>| {
+            Value::from_option(value, |t| Value::Text(timestamp::format_timestamp(&t)))
+        };
+
+        match column {
+            "job_id" => Value::Text(self.id()),
+            "name" => text(self.name.as_ref()),
+            "user" => text(self.user.as_ref()),
+            "account" => text(self.account.as_ref()),
+            "partition" => text(self.partition.as_ref()),
+            "qos" => text(self.qos.as_ref()),
+            "state" => Value::from_option(self.state.as_ref(), |s| Value::Text(s.to_string())),
+            "reason" => text(self.reason.as_ref()),
+            "time" => Value::from_option(self.run_time, Value::Duration),
+            "time_limit" => Value::from_option(self.time_limit, Value::Duration),
+            "nodes" => Value::from_option(self.num_nodes, |n| Value::Count(n.into())),
+            "cpus" => Value::from_option(self.cpus(), Value::Count),
+            "mem" => Value::from_option(self.memory(), Value::Memory),
+            "gres" => {
+                let gres: Vec<String> = self.gres().iter().map(Gres::to_string).collect();
+                if gres.is_empty() { Value::Empty } else { Value::Text(gres.join(",")) }
+            }
+            "nodelist" if self.nodes.is_empty() => Value::Empty,
+            "nodelist" => Value::Text(hostlist::compress(&self.nodes)),
+            "submit_time" => time(self.submit_time),
+            "start_time" => time(self.start_time),
+            "end_time" => time(self.end_time),
+            _ => Value::Empty,
+        }
+    }
+
+    /// Like `value`, with the state column coloured by state.
+    pub fn cell(&self, column: &str) -> Cell {
+        match (column, &self.state) {
+            ("state", Some(state)) => Cell::colored(self.value(column), state.color()),
+            _ => Cell::new(self.value(column)),
+        }
+    }
+}
+
+/// A job running on a node, as joined onto the node by `NodeMap::attach_jobs`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeJob {
+    pub job_id: String,
+    pub user: Option<String>,
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    pub state: Option<JobState>,
+    /// The job's allocation divided evenly over its nodes; `scontrol show job` only reports
+    /// totals.
+    pub tres: Tres,
+    pub run_time: Option<SlurmDuration>,
+    pub time_limit: Option<SlurmDuration>,
+}
+
+/// Columns `NodeJob::value` knows; `node` is filled in by the caller.
+pub const NODE_JOB_COLUMNS: &[&str] =
+    &["node", "job_id", "user", "account", "partition", "state", "cpus", "mem", "gres", "time", "time_limit"];
+
+impl NodeJob {
+    /// The value of one of `NODE_JOB_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        let text = |value: Option<&String>| Value::from_option(value.cloned(), Value::Text);
+
+        match column {
+            "job_id" => Value::Text(self.job_id.clone()),
+            "user" => text(self.user.as_ref()),
+            "account" => text(self.account.as_ref()),
+            "partition" => text(self.partition.as_ref()),
+            "state" => Value::from_option(self.state.as_ref(), |s| Value::Text(s.to_string())),
+            "cpus" => Value::from_option(self.tres.cpu(), Value::Count),
+            "mem" => Value::from_option(self.tres.mem(), Value::Memory),
+            "gres" => {
+                let gres: Vec<String> = self.tres.gres_list().iter().map(Gres::to_string).collect();
+                if gres.is_empty() { Value::Empty } else { Value::Text(gres.join(",")) }
+            }
+            "time" => Value::from_option(self.run_time, Value::Duration),
+            "time_limit" => Value::from_option(self.time_limit, Value::Duration),
+            _ => Value::Empty,
+        }
+    }
+}
+
+/// `alice(1000)` -> `alice`
+fn user_name(value: &str) -> &str {
+    value.split_once('(').map_or(value, |(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StaticSource;
+
+    const DUMP: &str = "\
+JobId=1001 JobName=train model UserId=alice(1000) GroupId=lab(100) Account=physics QOS=normal JobState=RUNNING Reason=None RunTime=01:00:00 TimeLimit=2-00:00:00 SubmitTime=2024-01-01T08:00:00 StartTime=2024-01-01T08:30:00 EndTime=2024-01-03T08:30:00 Partition=gpu NodeList=gpu[01-02] NumNodes=2 NumCPUs=64 ReqTRES=cpu=64,mem=200G,node=2,billing=64,gres/gpu=4 AllocTRES=cpu=64,mem=200G,node=2,billing=64,gres/gpu=4,gres/gpu:a100=4 Command=/home/alice/run.sh --epochs 10
+JobId=1003 ArrayJobId=1002 ArrayTaskId=3-10%2 JobName=sweep UserId=bob(1001) Account=chemistry JobState=PENDING Reason=Priority Partition=all,gpu NodeList=(null) NumNodes=1-1 NumCPUs=4 ReqTRES=cpu=4,mem=16G,node=1 AllocTRES=(null)
+";
+
+    #[test]
+    fn test_parse_jobs() {
+        let jobs = JobMap::build(&StaticSource::new(DUMP)).unwrap();
+        assert_eq!(jobs.jobs.len(), 2);
+
+        let running = jobs.get(1001).unwrap();
+        assert_eq!(running.name.as_deref(), Some("train model"));
+        assert_eq!(running.user.as_deref(), Some("alice"));
+        assert_eq!(running.state, Some(JobState::Running));
+        assert_eq!(running.reason, None);
+        assert_eq!(running.nodes, vec!["gpu01", "gpu02"]);
+        assert_eq!(running.time_limit, Some(SlurmDuration::from_secs(2 * 86400)));
+        assert_eq!(running.memory(), Some("200G".parse().unwrap()));
+        assert_eq!(running.value("gres"), Value::Text("gpu:a100:4".to_string()));
+        let share = running.node_share();
+        assert_eq!(share.job_id, "1001");
+        assert_eq!(share.value("cpus"), Value::Count(32));
+        assert_eq!(share.value("gres"), Value::Text("gpu:a100:2".to_string()));
+
+        let pending = jobs.get(1003).unwrap();
+        assert_eq!(pending.id(), "1002_[3-10%2]");
+        assert_eq!(pending.alloc_tres, None);
+        assert_eq!(pending.cpus(), Some(4));
+        assert!(pending.nodes.is_empty());
+        assert_eq!(pending.num_nodes, Some(1));
+        assert!(pending.in_partition(&["gpu".to_string()]));
+        assert!(pending.has_state(&["pd".to_string()]));
+        assert!(!pending.has_user(&["alice".to_string()]));
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use termcolor::Color;
+
+/// The state of a job, as printed by `scontrol show job` (`JobState=`) and `sacct` (`State`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobState {
+    Pending,
+    Configuring,
+    Running,
+    Suspended,
+    Completing,
+    Completed,
+    Cancelled,
+    Failed,
+    Timeout,
+    NodeFail,
+    Preempted,
+    BootFail,
+    Deadline,
+    OutOfMemory,
+    Requeued,
+    /// A state this version of slurmtool does not know about.
+    Other(String),
+}
+
+impl JobState {
+    /// Every known state with its scontrol name and its squeue short code.
+    const NAMES: [(JobState, &'static str, &'static str); 15] = [
+        (JobState::Pending, "PENDING", "PD"),
+        (JobState::Configuring, "CONFIGURING", "CF"),
+        (JobState::Running, "RUNNING", "R"),
+        (JobState::Suspended, "SUSPENDED", "S"),
+        (JobState::Completing, "COMPLETING", "CG"),
+        (JobState::Completed, "COMPLETED", "CD"),
+        (JobState::Cancelled, "CANCELLED", "CA"),
+        (JobState::Failed, "FAILED", "F"),
+        (JobState::Timeout, "TIMEOUT", "TO"),
+        (JobState::NodeFail, "NODE_FAIL", "NF"),
+        (JobState::Preempted, "PREEMPTED", "PR"),
+        (JobState::BootFail, "BOOT_FAIL", "BF"),
+        (JobState::Deadline, "DEADLINE", "DL"),
+        (JobState::OutOfMemory, "OUT_OF_MEMORY", "OOM"),
+        (JobState::Requeued, "REQUEUED", "RQ"),
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            JobState::Other(name) => name,
+            known => JobState::NAMES
+                .iter()
+                .find(|(state, _, _)| state == known)
+                .map_or("UNKNOWN", |(_, name, _)| name),
+        }
+    }
+
+    /// Whether the job holds resources on its nodes.
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobState::Running | JobState::Suspended | JobState::Completing | JobState::Configuring)
+    }
+
+    /// Whether the job has ended, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        !self.is_active() && !matches!(self, JobState::Pending | JobState::Requeued)
+    }
+
+    /// Whether `name` is this state's scontrol name or squeue short code, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim().to_uppercase();
+        self.as_str() == name
+            || JobState::NAMES
+                .iter()
+                .any(|(state, _, code)| state == self && *code == name)
+    }
+
+    /// Suggested colour when printing the state to a terminal.
+    pub fn color(&self) -> Color {
+        match self {
+            JobState::Running | JobState::Completed => Color::Green,
+            JobState::Pending | JobState::Requeued | JobState::Configuring => Color::Yellow,
+            JobState::Completing | JobState::Suspended => Color::Cyan,
+            JobState::Cancelled | JobState::Preempted => Color::Magenta,
+            JobState::Other(_) => Color::White,
+            _ => Color::Red,
+        }
+    }
+}
+
+impl FromStr for JobState {
+    type Err = String;
+
+    /// Accepts the scontrol and sacct spellings; sacct's `CANCELLED by 1234` keeps only the
+    /// state.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = value.split_whitespace().next().ok_or("Empty job state")?.to_uppercase();
+        Ok(JobState::NAMES
+            .iter()
+            .find(|(_, known, _)| *known == name)
+            .map_or(JobState::Other(name), |(state, _, _)| state.clone()))
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for JobState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_job_state() {
+        assert_eq!("RUNNING".parse::<JobState>().unwrap(), JobState::Running);
+        assert_eq!("CANCELLED by 1000".parse::<JobState>().unwrap(), JobState::Cancelled);
+        assert_eq!("out_of_memory".parse::<JobState>().unwrap(), JobState::OutOfMemory);
+        assert_eq!("RESIZING".parse::<JobState>().unwrap(), JobState::Other("RESIZING".to_string()));
+        assert!("".parse::<JobState>().is_err());
+
+        assert!(JobState::Pending.matches("pd"));
+        assert!(JobState::Pending.matches("pending"));
+        assert!(!JobState::Pending.matches("r"));
+        assert!(JobState::Timeout.is_finished());
+        assert!(JobState::Completing.is_active());
+    }
+}
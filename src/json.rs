@@ -0,0 +1,809 @@
+//! Parsing of `scontrol show <entity> --json` output.
+//!
+//! The JSON layout has changed between Slurm releases (plain numbers became
+//! `{"set", "infinite", "number"}` objects, strings became lists, partition fields were
+//! nested), so the wire types below accept every shape we have seen and convert to the same
+//! `Node`, `Partition`, `Job` and `Reservation` structs the oneliner parser produces.
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::gres;
+use crate::hostlist;
+use crate::job::Job;
+use crate::memory::Memory;
+use crate::node::Node;
+use crate::partition::Partition;
+use crate::reservation::Reservation;
+use crate::source::Entity;
+use crate::timestamp;
+
+/// Whether `text` looks like JSON rather than oneliner records.
+pub fn is_json(text: &str) -> bool {
+    text.trim_start().starts_with('{')
+}
+
+/// Keeps the documents of a (possibly concatenated) JSON dump that hold records for `entity`.
+pub fn select_documents(text: &str, entity: Entity) -> Result<String, SlurmToolError> {
+    let key = match entity {
+        Entity::Node => "nodes",
+        Entity::Partition => "partitions",
+        Entity::Job => "jobs",
+        Entity::Reservation => "reservations",
+    };
+
+    let mut selected = String::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>() {
+        let doc = doc.map_err(|source| SlurmToolError::Json { what: "scontrol", source })?;
+        if doc.get(key).is_some_and(|v| v.is_array()) {
+            selected.push_str(&doc.to_string());
+            selected.push('\n');
+        }
+    }
+    Ok(selected)
+}
+
+/// Parse one or more concatenated `scontrol show node --json` documents.
+pub fn parse_nodes(text: &str) -> Result<Vec<Node>, SlurmToolError> {
+    let mut nodes = Vec::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<NodesDocument>() {
+        let doc = doc.map_err(|source| SlurmToolError::Json { what: "scontrol node", source })?;
+        nodes.extend(doc.nodes.into_iter().map(Node::from));
+    }
+    Ok(nodes)
+}
+
+/// Parse one or more concatenated `scontrol show partition --json` documents.
+pub fn parse_partitions(text: &str) -> Result<Vec<Partition>, SlurmToolError> {
+    let mut partitions = Vec::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<PartitionsDocument>() {
+        let doc = doc.map_err(|source| SlurmToolError::Json { what: "scontrol partition", source })?;
+        partitions.extend(doc.partitions.into_iter().map(Partition::from));
+    }
+    Ok(partitions)
+}
+
+/// Parse one or more concatenated `scontrol show job --json` documents.
+pub fn parse_jobs(text: &str) -> Result<Vec<Job>, SlurmToolError> {
+    let mut jobs = Vec::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<JobsDocument>() {
+        let doc = doc.map_err(|source| SlurmToolError::Json { what: "scontrol job", source })?;
+        jobs.extend(doc.jobs.into_iter().map(Job::from));
+    }
+    Ok(jobs)
+}
+
+/// Parse one or more concatenated `scontrol show reservation --json` documents.
+pub fn parse_reservations(text: &str) -> Result<Vec<Reservation>, SlurmToolError> {
+    let mut reservations = Vec::new();
+    for doc in serde_json::Deserializer::from_str(text).into_iter::<ReservationsDocument>() {
+        let doc = doc.map_err(|source| SlurmToolError::Json { what: "scontrol reservation", source })?;
+        reservations.extend(doc.reservations.into_iter().map(Reservation::from));
+    }
+    Ok(reservations)
+}
+
+#[derive(Debug, Deserialize)]
+struct NodesDocument {
+    #[serde(default)]
+    nodes: Vec<JsonNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PartitionsDocument {
+    #[serde(default)]
+    partitions: Vec<JsonPartition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobsDocument {
+    #[serde(default)]
+    jobs: Vec<JsonJob>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReservationsDocument {
+    #[serde(default)]
+    reservations: Vec<JsonReservation>,
+}
+
+/// A number that is either plain or wrapped as `{"set": true, "infinite": false, "number": 5}`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+enum JsonNumber {
+    Plain(f64),
+    Flagged {
+        #[serde(default = "default_true")]
+        set: bool,
+        #[serde(default)]
+        infinite: bool,
+        #[serde(default)]
+        number: f64,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl JsonNumber {
+    /// The value, or `None` when it is unset or infinite.
+    fn value(self) -> Option<f64> {
+        match self {
+            JsonNumber::Plain(n) => Some(n),
+            JsonNumber::Flagged { set: true, infinite: false, number } => Some(number),
+            JsonNumber::Flagged { .. } => None,
+        }
+    }
+
+    fn is_infinite(self) -> bool {
+        matches!(self, JsonNumber::Flagged { infinite: true, .. })
+    }
+
+    fn as_u64(self) -> Option<u64> {
+        self.value().filter(|n| *n >= 0.0 && *n <= u64::MAX as f64).map(|n| n as u64)
+    }
+
+    fn as_u32(self) -> Option<u32> {
+        self.value().filter(|n| *n >= 0.0 && *n <= u32::MAX as f64).map(|n| n as u32)
+    }
+}
+
+/// A value that is either a single (possibly comma separated) string or a list of strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for StringOrList {
+    fn default() -> Self {
+        StringOrList::Many(Vec::new())
+    }
+}
+
+impl StringOrList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            StringOrList::One(s) => s
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+            StringOrList::Many(v) => v,
+        }
+    }
+
+    /// Joins the values the way the oneliner output does.
+    fn joined(self, separator: &str) -> Option<String> {
+        let values = self.into_vec();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.join(separator))
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonNode {
+    name: String,
+    architecture: Option<String>,
+    cores: Option<JsonNumber>,
+    alloc_cpus: Option<JsonNumber>,
+    effective_cpus: Option<JsonNumber>,
+    cpus: Option<JsonNumber>,
+    cpu_load: Option<JsonNumber>,
+    features: StringOrList,
+    active_features: StringOrList,
+    gres: Option<String>,
+    gres_used: Option<String>,
+    address: Option<String>,
+    hostname: Option<String>,
+    version: Option<String>,
+    operating_system: Option<String>,
+    real_memory: Option<JsonNumber>,
+    alloc_memory: Option<JsonNumber>,
+    sockets: Option<JsonNumber>,
+    boards: Option<JsonNumber>,
+    specialized_memory: Option<JsonNumber>,
+    state: StringOrList,
+    state_flags: StringOrList,
+    threads: Option<JsonNumber>,
+    temporary_disk: Option<JsonNumber>,
+    weight: Option<JsonNumber>,
+    owner: Option<String>,
+    mcs_label: Option<String>,
+    partitions: StringOrList,
+    boot_time: Option<JsonNumber>,
+    slurmd_start_time: Option<JsonNumber>,
+    last_busy: Option<JsonNumber>,
+    resume_after: Option<JsonNumber>,
+    tres: Option<String>,
+    tres_used: Option<String>,
+    reservation: Option<String>,
+    reason: Option<String>,
+}
+
+impl From<JsonNode> for Node {
+    fn from(json: JsonNode) -> Self {
+        let mut state = json.state.into_vec();
+        state.extend(json.state_flags.into_vec());
+        let state = state
+            .into_iter()
+            .map(|s| s.to_uppercase())
+            .collect::<Vec<_>>();
+
+        Node {
+            name: json.name,
+            arch: non_empty(json.architecture),
+            cores_per_socket: json.cores.and_then(JsonNumber::as_u32),
+            cpu_alloc: json.alloc_cpus.and_then(JsonNumber::as_u32),
+            cpu_effective: json.effective_cpus.and_then(JsonNumber::as_u32),
+            cpu_total: json.cpus.and_then(JsonNumber::as_u32),
+            // scontrol reports the load average multiplied by 100
+            cpu_load: json.cpu_load.and_then(JsonNumber::value).map(|load| load / 100.0),
+            available_features: json.features.into_vec(),
+            active_features: json.active_features.into_vec(),
+            gres: json.gres.and_then(|g| gres::parse_gres_list(&g).ok()).unwrap_or_default(),
+            gres_used: json.gres_used.and_then(|g| gres::parse_gres_list(&g).ok()).unwrap_or_default(),
+            addr: non_empty(json.address),
+            hostname: non_empty(json.hostname),
+            version: non_empty(json.version),
+            os: non_empty(json.operating_system),
+            real_memory: json.real_memory.and_then(JsonNumber::as_u64).map(Memory::new),
+            allocated_memory: json.alloc_memory.and_then(JsonNumber::as_u64).map(Memory::new),
+            sockets: json.sockets.and_then(JsonNumber::as_u32),
+            boards: json.boards.and_then(JsonNumber::as_u32),
+            mem_spec_limit: json.specialized_memory.and_then(JsonNumber::as_u64).map(Memory::new),
+            state: state.join("+").parse().ok(),
+            threads_per_core: json.threads.and_then(JsonNumber::as_u32),
+            tmp_disk: json.temporary_disk.and_then(JsonNumber::as_u32),
+            weight: json.weight.and_then(JsonNumber::as_u32),
+            owner: non_empty(json.owner),
+            mcs_label: non_empty(json.mcs_label),
+            partitions: json.partitions.into_vec(),
+            boot_time: json.boot_time.and_then(unix_timestamp),
+            slurmd_start_time: json.slurmd_start_time.and_then(unix_timestamp),
+            last_busy_time: json.last_busy.and_then(unix_timestamp),
+            resume_after_time: json.resume_after.and_then(unix_timestamp),
+            cfg_tres: json.tres.and_then(|t| t.parse().ok()),
+            alloc_tres: json.tres_used.and_then(|t| t.parse().ok()),
+            reservation_name: non_empty(json.reservation),
+            reason: non_empty(json.reason),
+            ..Node::default()
+        }
+    }
+}
+
+/// `nodes` is a plain hostlist in older releases and an object in newer ones.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonPartitionNodes {
+    Hostlist(String),
+    Detailed {
+        #[serde(default)]
+        allowed_allocation: Option<String>,
+        #[serde(default)]
+        configured: Option<String>,
+        #[serde(default)]
+        total: Option<JsonNumber>,
+    },
+}
+
+/// An object in newer releases that was a bare string in older ones.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ObjectOrString<T> {
+    Object(T),
+    Text(String),
+}
+
+impl<T> Default for ObjectOrString<T> {
+    fn default() -> Self {
+        ObjectOrString::Text(String::new())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonAllowDeny {
+    allowed: Option<String>,
+    assigned: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionCpus {
+    total: Option<JsonNumber>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionDefaults {
+    memory_per_cpu: Option<JsonNumber>,
+    time: Option<JsonNumber>,
+    job: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonOversubscribe {
+    jobs: Option<JsonNumber>,
+    flags: StringOrList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionMaximums {
+    cpus_per_node: Option<JsonNumber>,
+    cpus_per_socket: Option<JsonNumber>,
+    memory_per_node: Option<JsonNumber>,
+    partition_memory_per_node: Option<JsonNumber>,
+    nodes: Option<JsonNumber>,
+    time: Option<JsonNumber>,
+    over_time_limit: Option<JsonNumber>,
+    oversubscribe: Option<JsonOversubscribe>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionMinimums {
+    nodes: Option<JsonNumber>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionPriority {
+    job_factor: Option<JsonNumber>,
+    tier: Option<JsonNumber>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionTres {
+    billing_weights: Option<String>,
+    configured: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartitionState {
+    state: StringOrList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonPartition {
+    name: String,
+    nodes: Option<JsonPartitionNodes>,
+    accounts: ObjectOrString<JsonAllowDeny>,
+    groups: ObjectOrString<JsonAllowDeny>,
+    qos: ObjectOrString<JsonAllowDeny>,
+    cpus: JsonPartitionCpus,
+    defaults: JsonPartitionDefaults,
+    maximums: JsonPartitionMaximums,
+    minimums: JsonPartitionMinimums,
+    priority: JsonPartitionPriority,
+    tres: ObjectOrString<JsonPartitionTres>,
+    partition: JsonPartitionState,
+    grace_time: Option<JsonNumber>,
+    flags: StringOrList,
+    select_type: StringOrList,
+    // Flat layout used before Slurm 23.02
+    total_cpus: Option<JsonNumber>,
+    total_nodes: Option<JsonNumber>,
+    max_time_limit: Option<JsonNumber>,
+    default_time_limit: Option<JsonNumber>,
+    allowed_accounts: Option<String>,
+    allowed_groups: Option<String>,
+    allowed_qos: Option<String>,
+    billing_weights: Option<String>,
+    state: Option<String>,
+}
+
+impl From<JsonPartition> for Partition {
+    fn from(json: JsonPartition) -> Self {
+        let mut partition = Partition {
+            name: json.name,
+            ..Partition::default()
+        };
+
+        match json.nodes {
+            Some(JsonPartitionNodes::Hostlist(nodes)) => partition.nodes = expand_nodes(&format!("partition '{}'", partition.name), &nodes),
+            Some(JsonPartitionNodes::Detailed { allowed_allocation, configured, total }) => {
+                partition.alloc_nodes = non_empty(allowed_allocation);
+                if let Some(configured) = configured {
+                    partition.nodes = expand_nodes(&format!("partition '{}'", partition.name), &configured);
+                }
+                partition.total_nodes = total.and_then(JsonNumber::as_u32);
+            }
+            None => {}
+        }
+
+        let (accounts, groups, qos) = (allowed(json.accounts), allowed(json.groups), json.qos);
+        partition.allow_accounts = accounts
+            .or(json.allowed_accounts)
+            .map(|a| a.split(',').filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        partition.allow_groups = non_empty(groups.or(json.allowed_groups));
+        match qos {
+            ObjectOrString::Object(qos) => {
+                partition.allow_qos = non_empty(qos.allowed);
+                partition.qos = non_empty(qos.assigned);
+            }
+            ObjectOrString::Text(qos) => {
+                partition.allow_qos = non_empty(json.allowed_qos);
+                partition.qos = non_empty(Some(qos));
+            }
+        }
+        partition.total_cpus = json.cpus.total.or(json.total_cpus).and_then(JsonNumber::as_u32);
+        if partition.total_nodes.is_none() {
+            partition.total_nodes = json.total_nodes.and_then(JsonNumber::as_u32);
+        }
+
+        partition.def_mem_per_cpu = json.defaults.memory_per_cpu.and_then(JsonNumber::as_u64).map(Memory::new);
+        partition.default_time = json
+            .defaults
+            .time
+            .or(json.default_time_limit)
+            .and_then(minutes_or_unset);
+        partition.job_defaults = non_empty(json.defaults.job);
+
+        let maximums = json.maximums;
+        partition.max_cpus_per_node = maximums.cpus_per_node.map(format_limit);
+        partition.max_cpus_per_socket = maximums.cpus_per_socket.map(format_limit);
+        partition.max_mem_per_node = maximums
+            .memory_per_node
+            .or(maximums.partition_memory_per_node)
+            .and_then(JsonNumber::as_u64)
+            .map(Memory::new);
+        partition.max_nodes = maximums.nodes.and_then(JsonNumber::as_u32);
+        partition.max_time = maximums
+            .time
+            .or(json.max_time_limit)
+            .map(|t| minutes_or_unset(t).unwrap_or(SlurmDuration::Unlimited));
+        partition.over_time_limit = maximums.over_time_limit.and_then(minutes_or_unset);
+        if let Some(oversubscribe) = maximums.oversubscribe {
+            let flags = oversubscribe.flags.into_vec();
+            partition.oversubscribe = oversubscribe.jobs.and_then(JsonNumber::value).is_some_and(|jobs| jobs > 1.0)
+                || flags.iter().any(|f| f.eq_ignore_ascii_case("force"));
+        }
+
+        partition.min_nodes = json.minimums.nodes.and_then(JsonNumber::as_u32);
+        partition.priority_job_factor = json.priority.job_factor.and_then(JsonNumber::as_u32);
+        partition.priority_tier = json.priority.tier.and_then(JsonNumber::as_u32);
+        match json.tres {
+            ObjectOrString::Object(tres) => {
+                partition.tres = tres.configured.and_then(|t| t.parse().ok());
+                partition.tres_billing_weights = tres.billing_weights.and_then(|w| w.parse().ok());
+            }
+            ObjectOrString::Text(tres) => {
+                partition.tres = tres.parse().ok();
+                partition.tres_billing_weights = json.billing_weights.and_then(|w| w.parse().ok());
+            }
+        }
+        partition.grace_time = json.grace_time.and_then(JsonNumber::as_u32);
+        partition.select_type_parameters = json.select_type.joined(",");
+
+        partition.state = json
+            .partition
+            .state
+            .joined("+")
+            .or(json.state)
+            .map(|s| s.to_uppercase());
+
+        for flag in json.flags.into_vec() {
+            match flag.to_lowercase().as_str() {
+                "default" => partition.default = true,
+                "hidden" => partition.hidden = true,
+                "no_root" => partition.disable_root_jobs = true,
+                "exclusive_user" => partition.exclusive_user = true,
+                "least_loaded_nodes" | "lln" => partition.lln = true,
+                "root_only" => partition.root_only = true,
+                "req_resv" | "reservation_required" => partition.req_resv = true,
+                _ => {}
+            }
+        }
+
+        partition
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonJob {
+    job_id: Option<JsonNumber>,
+    array_job_id: Option<JsonNumber>,
+    array_task_id: Option<JsonNumber>,
+    array_task_string: Option<String>,
+    name: Option<String>,
+    user_name: Option<String>,
+    account: Option<String>,
+    partition: Option<String>,
+    qos: Option<String>,
+    job_state: StringOrList,
+    state_reason: Option<String>,
+    tres_req_str: Option<String>,
+    tres_alloc_str: Option<String>,
+    nodes: Option<String>,
+    node_count: Option<JsonNumber>,
+    cpus: Option<JsonNumber>,
+    submit_time: Option<JsonNumber>,
+    start_time: Option<JsonNumber>,
+    end_time: Option<JsonNumber>,
+    time_limit: Option<JsonNumber>,
+}
+
+impl From<JsonJob> for Job {
+    fn from(json: JsonJob) -> Self {
+        let job_id = json.job_id.and_then(JsonNumber::as_u64).unwrap_or_default();
+        // Array fields are zero or unset for jobs that are not part of an array.
+        let array_job_id = json.array_job_id.and_then(JsonNumber::as_u64).filter(|id| *id != 0);
+        let array_task_id = json
+            .array_task_id
+            .and_then(JsonNumber::as_u64)
+            .map(|task| task.to_string())
+            .or(non_empty(json.array_task_string))
+            .filter(|_| array_job_id.is_some());
+        let state = json.job_state.into_vec().first().and_then(|state| state.parse().ok());
+
+        let mut job = Job {
+            job_id,
+            array_job_id,
+            array_task_id,
+            name: non_empty(json.name),
+            user: non_empty(json.user_name),
+            account: non_empty(json.account),
+            partition: non_empty(json.partition),
+            qos: non_empty(json.qos),
+            state,
+            reason: non_empty(json.state_reason).filter(|reason| reason != "None"),
+            req_tres: json.tres_req_str.and_then(|tres| tres.parse().ok()),
+            alloc_tres: json.tres_alloc_str.and_then(|tres| tres.parse().ok()),
+            nodes: non_empty(json.nodes)
+                .map(|nodes| expand_nodes(&format!("job {}", job_id), &nodes))
+                .unwrap_or_default(),
+            num_nodes: json.node_count.and_then(JsonNumber::as_u32),
+            num_cpus: json.cpus.and_then(JsonNumber::as_u32),
+            submit_time: json.submit_time.and_then(unix_timestamp),
+            start_time: json.start_time.and_then(unix_timestamp),
+            end_time: json.end_time.and_then(unix_timestamp),
+            time_limit: json.time_limit.and_then(minutes_or_unset),
+            run_time: None,
+        };
+        // The JSON has no run time; derive it like scontrol does.
+        if let Some(start) = job.start_time {
+            let now = Local::now();
+            let end = match &job.state {
+                Some(state) if state.is_active() => now,
+                _ => job.end_time.unwrap_or(now).min(now),
+            };
+            job.run_time = Some(timestamp::elapsed(&start, &end));
+        }
+        job
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonReservation {
+    name: String,
+    start_time: Option<JsonNumber>,
+    end_time: Option<JsonNumber>,
+    node_list: Option<String>,
+    node_count: Option<JsonNumber>,
+    core_count: Option<JsonNumber>,
+    partition: Option<String>,
+    features: Option<String>,
+    flags: StringOrList,
+    users: StringOrList,
+    accounts: StringOrList,
+}
+
+impl From<JsonReservation> for Reservation {
+    fn from(json: JsonReservation) -> Self {
+        let start_time = json.start_time.and_then(unix_timestamp);
+        let end_time = json.end_time.and_then(unix_timestamp);
+        // The JSON has no state; derive it like scontrol does.
+        let now = Local::now();
+        let active = start_time.is_some_and(|start| start <= now) && end_time.is_none_or(|end| now < end);
+
+        Reservation {
+            nodes: non_empty(json.node_list)
+                .map(|nodes| expand_nodes(&format!("reservation '{}'", json.name), &nodes))
+                .unwrap_or_default(),
+            name: json.name,
+            start_time,
+            end_time,
+            duration: start_time.zip(end_time).map(|(start, end)| timestamp::elapsed(&start, &end)),
+            node_count: json.node_count.and_then(JsonNumber::as_u32),
+            core_count: json.core_count.and_then(JsonNumber::as_u32),
+            partition: non_empty(json.partition),
+            features: non_empty(json.features),
+            flags: json.flags.into_vec(),
+            users: json.users.into_vec(),
+            accounts: json.accounts.into_vec(),
+            state: Some(if active { "ACTIVE" } else { "INACTIVE" }.to_string()),
+        }
+    }
+}
+
+/// Expands a node list; `owner` (e.g. `partition 'gpu'`) names it in the warning.
+fn expand_nodes(owner: &str, nodes: &str) -> Vec<String> {
+    hostlist::expand(nodes).unwrap_or_else(|e| {
+        log::warn!("Ignoring node list of {}: {}", owner, e);
+        Vec::new()
+    })
+}
+
+/// The allow list of an access field, whichever layout it came in.
+fn allowed(value: ObjectOrString<JsonAllowDeny>) -> Option<String> {
+    match value {
+        ObjectOrString::Object(access) => non_empty(access.allowed),
+        ObjectOrString::Text(text) => non_empty(Some(text)),
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.is_empty())
+}
+
+/// Formats a count limit the way the oneliner output does.
+fn format_limit(value: JsonNumber) -> String {
+    match value.value() {
+        Some(n) => format!("{}", n as u64),
+        None => "UNLIMITED".to_string(),
+    }
+}
+
+/// A limit in minutes; `None` when unset, `Unlimited` when infinite.
+fn minutes_or_unset(value: JsonNumber) -> Option<SlurmDuration> {
+    if value.is_infinite() {
+        return Some(SlurmDuration::Unlimited);
+    }
+    value.value().and_then(|minutes| SlurmDuration::from_minutes(minutes as u64))
+}
+
+fn unix_timestamp(value: JsonNumber) -> Option<DateTime<Local>> {
+    value.value().and_then(|secs| timestamp::from_unix(secs as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nodes_json() {
+        let json = r#"{
+            "nodes": [
+                {
+                    "name": "node01",
+                    "architecture": "x86_64",
+                    "cores": 16,
+                    "cpus": 32,
+                    "alloc_cpus": 8,
+                    "cpu_load": 102,
+                    "features": ["avx2", "avx512"],
+                    "operating_system": "Linux 4.18.0-477.el8.x86_64 #1 SMP",
+                    "real_memory": 256000,
+                    "alloc_memory": 64000,
+                    "state": ["MIXED", "DRAIN"],
+                    "partitions": ["all", "gpu"],
+                    "boot_time": {"set": true, "infinite": false, "number": 0},
+                    "reason": "Not responding [slurm@2024-01-01T00:00:00]"
+                }
+            ],
+            "meta": {}
+        }"#;
+
+        let nodes = parse_nodes(json).unwrap();
+
+        assert_eq!(nodes.len(), 1);
+        let node = &nodes[0];
+        assert_eq!(node.name, "node01");
+        assert_eq!(node.cpu_total, Some(32));
+        assert_eq!(node.cpu_load, Some(1.02));
+        assert_eq!(node.available_features, vec!["avx2", "avx512"]);
+        assert_eq!(node.os.as_deref(), Some("Linux 4.18.0-477.el8.x86_64 #1 SMP"));
+        assert_eq!(node.state.as_ref().unwrap().to_string(), "MIXED+DRAIN");
+        assert_eq!(node.boot_time, None);
+        assert_eq!(node.reason.as_deref(), Some("Not responding [slurm@2024-01-01T00:00:00]"));
+    }
+
+    #[test]
+    fn test_parse_partitions_json() {
+        let json = r#"{
+            "partitions": [
+                {
+                    "name": "gpu",
+                    "nodes": {"allowed_allocation": "", "configured": "gpu[01-02]", "total": 2},
+                    "accounts": {"allowed": "lab1,lab2", "deny": ""},
+                    "cpus": {"task_binding": 0, "total": 128},
+                    "defaults": {"time": {"set": true, "infinite": false, "number": 60}},
+                    "maximums": {
+                        "nodes": {"set": false, "infinite": true, "number": 0},
+                        "time": {"set": true, "infinite": false, "number": 10080}
+                    },
+                    "partition": {"state": ["UP"]},
+                    "flags": ["default"]
+                }
+            ]
+        }"#;
+
+        let partitions = parse_partitions(json).unwrap();
+
+        assert_eq!(partitions.len(), 1);
+        let gpu = &partitions[0];
+        assert_eq!(gpu.nodes, vec!["gpu01", "gpu02"]);
+        assert_eq!(gpu.allow_accounts, vec!["lab1", "lab2"]);
+        assert_eq!(gpu.total_cpus, Some(128));
+        assert_eq!(gpu.default_time, Some(SlurmDuration::from_minutes(60).unwrap()));
+        assert_eq!(gpu.max_time, Some(SlurmDuration::from_minutes(7 * 1440).unwrap()));
+        assert_eq!(gpu.max_nodes, None);
+        assert_eq!(gpu.state.as_deref(), Some("UP"));
+        assert!(gpu.default);
+    }
+
+    #[test]
+    fn test_parse_flat_partition_json() {
+        let json = r#"{"partitions": [{"name": "debug", "nodes": "node[1-2]", "total_cpus": 8,
+            "max_time_limit": 30, "state": "UP", "qos": "normal", "tres": "cpu=8"}]}"#;
+
+        let partitions = parse_partitions(json).unwrap();
+
+        assert_eq!(partitions[0].nodes, vec!["node1", "node2"]);
+        assert_eq!(partitions[0].total_cpus, Some(8));
+        assert_eq!(partitions[0].max_time, Some(SlurmDuration::from_minutes(30).unwrap()));
+        assert_eq!(partitions[0].qos.as_deref(), Some("normal"));
+        assert_eq!(partitions[0].tres.as_ref().unwrap().cpu(), Some(8));
+    }
+
+    #[test]
+    fn test_parse_jobs_json() {
+        let json = r#"{"jobs": [{
+            "job_id": 1001, "name": "train", "user_name": "alice", "account": "physics",
+            "partition": "gpu", "job_state": ["RUNNING"], "state_reason": "None",
+            "array_job_id": {"set": true, "infinite": false, "number": 0},
+            "tres_alloc_str": "cpu=64,mem=200G,node=2,gres/gpu=4", "nodes": "gpu[01-02]",
+            "node_count": {"set": true, "infinite": false, "number": 2},
+            "time_limit": {"set": true, "infinite": false, "number": 2880}
+        }]}"#;
+
+        let jobs = parse_jobs(json).unwrap();
+
+        let job = &jobs[0];
+        assert_eq!(job.job_id, 1001);
+        assert_eq!(job.array_job_id, None);
+        assert_eq!(job.state.as_ref().map(|s| s.to_string()).as_deref(), Some("RUNNING"));
+        assert_eq!(job.reason, None);
+        assert_eq!(job.nodes, vec!["gpu01", "gpu02"]);
+        assert_eq!(job.num_nodes, Some(2));
+        assert_eq!(job.time_limit, Some(SlurmDuration::from_minutes(2880).unwrap()));
+        assert_eq!(job.alloc_tres.as_ref().unwrap().gres("gpu"), Some(4));
+    }
+
+    #[test]
+    fn test_parse_reservations_json() {
+        let json = r#"{"reservations": [{
+            "name": "maint", "node_list": "node[01-03]", "node_count": 3, "users": "root",
+            "accounts": "", "flags": ["MAINT", "IGNORE_JOBS"],
+            "start_time": {"set": true, "infinite": false, "number": 4102444800},
+            "end_time": {"set": true, "infinite": false, "number": 4102488000}
+        }]}"#;
+
+        let reservations = parse_reservations(json).unwrap();
+
+        let maint = &reservations[0];
+        assert_eq!(maint.nodes, vec!["node01", "node02", "node03"]);
+        assert_eq!(maint.users, vec!["root"]);
+        assert!(maint.accounts.is_empty());
+        assert!(maint.is_maintenance());
+        assert_eq!(maint.state.as_deref(), Some("INACTIVE"));
+        assert_eq!(maint.duration, Some(SlurmDuration::from_secs(12 * 3600)));
+    }
+}
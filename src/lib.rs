@@ -1,4 +1,28 @@
+pub mod duration;
+pub mod efficiency;
+pub mod error;
+pub mod filter;
+pub mod fit;
+pub mod gres;
+pub mod hostlist;
+pub mod job;
+pub mod job_state;
+pub mod json;
+pub mod memory;
 pub mod node;
+pub mod node_state;
+pub mod oneliner;
+pub mod output;
 pub mod partition;
+pub mod qos;
+pub mod reservation;
+pub mod sacct;
+pub mod source;
+pub mod table;
 pub mod terminal_size;
-pub mod progress;
\ No newline at end of file
+pub mod timestamp;
+pub mod tres;
+pub mod tui;
+pub mod progress;
+
+pub use error::SlurmToolError;
@@ -1,138 +1,1063 @@
 use std::error::Error;
-use clap::{ Parser, Subcommand };
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use chrono::{ DateTime, Local };
+use clap::{ error::ErrorKind, Args, CommandFactory, Parser, Subcommand };
+use serde::Serialize;
+use termcolor::{ ColorChoice, StandardStream };
 
-use terminal_size::{ Width, Height, terminal_size };
 
 // use slurmtool::progress::{new_progress_bar};
-pub mod partition;
-pub mod node;
-use partition::PartitionMap;
-use node::NodeMap;
+use slurmtool::duration::SlurmDuration;
+use slurmtool::efficiency::{ self, JobEfficiency, EFFICIENCY_COLUMNS, USER_EFFICIENCY_COLUMNS };
+use slurmtool::filter::NodeFilter;
+use slurmtool::fit::{ self, FitStatus, JobRequest, PartitionFit };
+use slurmtool::gres::{ self, Gres };
+use slurmtool::hostlist;
+use slurmtool::job::{ Job, JobMap, JOB_COLUMNS, NODE_JOB_COLUMNS };
+use slurmtool::partition::{ Partition, PartitionMap, PartitionSummary };
+use slurmtool::qos::{ PartitionLimits, QosMap, LIMIT_COLUMNS };
+use slurmtool::reservation::{ NodeReservation, Reservation, ReservationMap, RESERVATION_COLUMNS };
+use slurmtool::sacct::{ AccountingRecord, HistoryQuery, HISTORY_COLUMNS };
+use slurmtool::node::{ DisplayUnits, Memory, Node, NodeMap, NODE_COLUMNS };
+use slurmtool::error::exit_code;
+use slurmtool::SlurmToolError;
+use slurmtool::output::{ self, OutputFormat };
+use slurmtool::table::{ self, Cell, SortKey, Table, Value };
+use slurmtool::terminal_size::TerminalSize;
+use slurmtool::timestamp::{ self, TimeSpec };
+use slurmtool::source::{ FileSource, ScontrolSource, SlurmSource, StdinSource };
 
 /// CLI Application to fetch node details for a specific partition
 #[derive(Parser)]
 #[command(name = "Partition Node Viewer")]
 #[command(about = "CLI to fetch and display node details for a partition", version = "1.0")]
+#[command(after_help = EXIT_CODES)]
 struct Cli {
+    /// Read captured `scontrol show ... --oneliner` (and `sacct --parsable2`) output from
+    /// these files instead of running Slurm commands. Use `-` alone to read from stdin.
+    #[arg(long, global = true, value_name = "FILE")]
+    input: Vec<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputOptions,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    /// Picks the data source requested on the command line, defaulting to live `scontrol`.
+    /// Exits with a usage error when `-` is mixed with file paths.
+    fn source(&self) -> Box<dyn SlurmSource> {
+        if self.input.is_empty() {
+            Box::new(ScontrolSource::new())
+        } else if self.input.iter().any(|path| path.as_os_str() == "-") {
+            if self.input.iter().any(|path| path.as_os_str() != "-") {
+                Cli::command()
+                    .bin_name(env!("CARGO_BIN_NAME"))
+                    .error(ErrorKind::ArgumentConflict, "--input - reads stdin and cannot be combined with other inputs")
+                    .exit();
+            }
+            Box::new(StdinSource::new())
+        } else {
+            Box::new(FileSource::new(self.input.clone()))
+        }
+    }
+}
+
+/// How results are rendered, shared by every subcommand
+#[derive(Args, Clone, Copy)]
+struct OutputOptions {
+    /// Units for memory sizes: auto, kib, mib, gib, tib (powers of 1024), or si, kb, mb, gb,
+    /// tb (powers of 1000)
+    #[arg(long, global = true, default_value = "auto", value_name = "UNITS")]
+    units: DisplayUnits,
+
+    /// Output format: text, json, yaml, csv or tsv
+    #[arg(long, global = true, default_value = "text", value_name = "FORMAT")]
+    format: OutputFormat,
+}
+
+/// The `--where` node filter, shared by the commands that select nodes
+#[derive(Args)]
+struct NodeFilterArgs {
+    /// Only include nodes matching this expression, e.g.
+    /// 'state=idle && cpu_free>=32 && mem_free>=256G && feature=avx512'
+    #[arg(short = 'w', long = "where", value_name = "EXPR")]
+    filter: Option<NodeFilter>,
+}
+
+#[derive(Args)]
+struct NodesArgs {
+    /// The name of the partition to fetch nodes from
+    #[arg(short, long)]
+    partition: String,
+
+    /// The number of nodes to display in the partition
+    /// Default is all (0)
+    #[arg(short, long)]
+    limit: Option<usize>,
+
+    /// Only show nodes in this state, e.g. idle, mixed, drain, draining, not_responding
+    /// or available
+    #[arg(short, long)]
+    state: Option<String>,
+
+    /// Only show idle nodes that have had no jobs for at least this long, e.g. 2d or 12h
+    #[arg(long, value_name = "DURATION")]
+    idle_since: Option<SlurmDuration>,
+
+    #[command(flatten)]
+    node_filter: NodeFilterArgs,
+
+    /// Comma separated columns to show, from: name, state, partitions, cpu_total, cpu_alloc,
+    /// cpu_free, cpu_load, mem_total, mem_alloc, mem_free, gres, gres_used, gres_free,
+    /// features, uptime, idle_for, reason, jobs, users, next_reservation
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "name,state,cpu_free,cpu_total,mem_free,mem_total,gres,idle_for,reason"
+    )]
+    columns: Vec<String>,
+
+    /// Sort by these columns, each optionally suffixed with :asc or :desc, e.g. mem_free:desc
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN[:asc|desc]")]
+    sort: Vec<SortKey>,
+
+    /// Also list the jobs running on each node with their share of its resources
+    #[arg(long)]
+    show_jobs: bool,
+
+    /// Also list upcoming reservations that will take nodes which are idle now
+    #[arg(long)]
+    show_reservations: bool,
+
+    /// to debug, print all attributes of the node struct
+    /// Default is false
+    #[arg(short, long)]
+    debug: bool,
+}
+
+#[derive(Args)]
+struct JobsArgs {
+    /// Only show jobs of these users (comma separated)
+    #[arg(short, long, value_delimiter = ',')]
+    user: Vec<String>,
+
+    /// Only show jobs in these partitions (comma separated)
+    #[arg(short, long, value_delimiter = ',')]
+    partition: Vec<String>,
+
+    /// Only show jobs in these states (comma separated), e.g. running,pending or R,PD
+    #[arg(short, long, value_delimiter = ',')]
+    state: Vec<String>,
+
+    /// Comma separated columns to show, from: job_id, name, user, account, partition, qos,
+    /// state, reason, time, time_limit, nodes, cpus, mem, gres, nodelist, submit_time,
+    /// start_time, end_time
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "job_id,partition,name,user,state,time,time_limit,nodes,cpus,mem,gres,nodelist,reason"
+    )]
+    columns: Vec<String>,
+
+    /// Sort by these columns, each optionally suffixed with :asc or :desc, e.g. time:desc
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN[:asc|desc]")]
+    sort: Vec<SortKey>,
+}
+
+/// Which jobs to look up in the accounting database, shared by `history` and `efficiency`
+#[derive(Args)]
+struct AccountingArgs {
+    /// Only show these jobs or job arrays (comma separated), whenever they ran
+    #[arg(short, long, value_delimiter = ',')]
+    jobs: Vec<String>,
+
+    /// Only show jobs of these users (comma separated). Default is your own jobs
+    #[arg(short, long, value_delimiter = ',')]
+    user: Vec<String>,
+
+    /// Show jobs of every user
+    #[arg(short, long, conflicts_with = "user")]
+    all_users: bool,
+
+    /// Only show jobs charged to these accounts (comma separated)
+    #[arg(short = 'A', long, value_delimiter = ',')]
+    account: Vec<String>,
+
+    /// Only show jobs that ran after this time: now, a date like 2024-01-31T08:00 or a
+    /// duration ago like 7d
+    #[arg(short = 'S', long, default_value = "1d", value_name = "TIME")]
+    start: TimeSpec,
+
+    /// Only show jobs that ran before this time, in the same formats as --start
+    #[arg(short = 'E', long, default_value = "now", value_name = "TIME")]
+    end: TimeSpec,
+
+    /// Only show jobs that ended in these states (comma separated), e.g. failed,timeout or F,TO
+    #[arg(short, long, value_delimiter = ',')]
+    state: Vec<String>,
+}
+
+impl AccountingArgs {
+    fn query(&self, allocations_only: bool) -> HistoryQuery {
+        let now = Local::now();
+        HistoryQuery {
+            jobs: self.jobs.clone(),
+            users: self.user.clone(),
+            all_users: self.all_users,
+            accounts: self.account.clone(),
+            states: self.state.clone(),
+            start: self.start.resolve(&now),
+            end: self.end.resolve(&now),
+            allocations_only,
+        }
+    }
+}
+
+#[derive(Args)]
+struct HistoryArgs {
+    #[command(flatten)]
+    selection: AccountingArgs,
+
+    /// Only show job allocations, not their steps
+    #[arg(short = 'X', long)]
+    allocations: bool,
+
+    /// Comma separated columns to show, from: job_id, name, user, account, partition, state,
+    /// exit_code, elapsed, time_limit, total_cpu, max_rss, nodes, cpus, req_mem, req_tres,
+    /// alloc_tres, nodelist, submit_time, start_time, end_time
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "job_id,name,user,account,partition,state,exit_code,elapsed,total_cpu,max_rss,req_tres"
+    )]
+    columns: Vec<String>,
+
+    /// Sort by these columns, each optionally suffixed with :asc or :desc, e.g. elapsed:desc.
+    /// Default is sacct's order, steps after their job
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN[:asc|desc]")]
+    sort: Vec<SortKey>,
+}
+
+#[derive(Args)]
+struct EfficiencyArgs {
+    /// A job or job array id; the same as --jobs
+    job: Option<String>,
+
+    #[command(flatten)]
+    selection: AccountingArgs,
+
+    /// Comma separated columns to show, from: job_id, name, user, account, partition, state,
+    /// elapsed, nodes, cpus, cpu_used, cpu_eff, mem_used, mem_req, mem_eff, gpus, gpu_hours
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "job_id,user,state,elapsed,cpus,cpu_eff,mem_used,mem_req,mem_eff,gpu_hours"
+    )]
+    columns: Vec<String>,
+
+    /// Sort by these columns, each optionally suffixed with :asc or :desc, e.g. mem_eff
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN[:asc|desc]")]
+    sort: Vec<SortKey>,
+
+    /// How many users to list in the worst offenders summary; 0 leaves it out
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Args)]
+struct FitArgs {
+    /// CPUs per node
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    cpus: u32,
+
+    /// Memory per node, e.g. 200G. Default is the partition's DefMemPerCPU times --cpus
+    #[arg(long)]
+    mem: Option<Memory>,
+
+    /// Comma separated GRES per node, e.g. gpu:a100:2
+    #[arg(long, value_delimiter = ',')]
+    gres: Vec<Gres>,
+
+    /// Time limit, e.g. 2-00:00:00. Default is the partition's default time
+    #[arg(short, long)]
+    time: Option<SlurmDuration>,
+
+    /// Number of nodes
+    #[arg(short = 'N', long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    nodes: u32,
+
+    /// Account to check against AllowAccounts
+    #[arg(short = 'A', long)]
+    account: Option<String>,
+
+    /// QOS to check against AllowQos
+    #[arg(short, long)]
+    qos: Option<String>,
+
+    /// Only check this partition. Default is all partitions
+    #[arg(short, long)]
+    partition: Option<String>,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Fetch nodes for a specific partition
-    Nodes {
+    Nodes(NodesArgs),
+    /// Show every detail of one node and the jobs running on it
+    Node {
+        /// The node name
+        name: String,
+    },
+    /// Group the nodes of a partition by CPU count and memory
+    GroupNodes {
         /// The name of the partition to fetch nodes from
         #[arg(short, long)]
         partition: String,
 
-        /// The number of nodes to display in the partition
-        /// Default is all (0)
+        #[command(flatten)]
+        node_filter: NodeFilterArgs,
+    },
+    /// Show GPU (or other GRES) inventory per partition and type with used/free totals
+    Gres {
+        /// Only show this partition. Default is all partitions
         #[arg(short, long)]
-        limit: Option<usize>,
+        partition: Option<String>,
+
+        /// The GRES to count
+        #[arg(short, long, default_value = "gpu")]
+        name: String,
 
-        /// to debug, print all attributes of the node struct
-        /// Default is false
+        #[command(flatten)]
+        node_filter: NodeFilterArgs,
+    },
+    /// List jobs, optionally filtered by user, partition and state
+    Jobs(JobsArgs),
+    /// List past jobs and their steps from the accounting database (sacct)
+    History(HistoryArgs),
+    /// Show how much of their requested CPUs, memory and GPUs jobs used, like seff
+    Efficiency(EfficiencyArgs),
+    /// Summarize every partition: state, time limits, nodes by state and free resources
+    Partitions {
+        /// Also show hidden partitions
         #[arg(short, long)]
-        debug: bool,
+        all: bool,
     },
-    GroupNodes {
-        /// The name of the partition to fetch nodes from
+    /// Show each partition's effective limits, combining its partition QoS with its own limits
+    Qos {
+        /// Only show this partition. Default is all partitions
         #[arg(short, long)]
-        partition: String,
+        partition: Option<String>,
+
+        /// Also show hidden partitions
+        #[arg(short, long)]
+        all: bool,
     },
+    /// List reservations with their time window, nodes and who may use them
+    Reservations {
+        /// Only show reservations that include this node
+        #[arg(short, long)]
+        node: Option<String>,
+    },
+    /// Interactive dashboard of partitions, node utilization and node details
+    Tui {
+        /// Seconds between refreshes; 0 only refreshes on `r`
+        #[arg(short, long, default_value_t = 10)]
+        interval: u64,
+    },
+    /// Show which partitions can run a job with the given resources, now or eventually
+    Fit(FitArgs),
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let size = terminal_size();
-    if let Some((Width(w), Height(h))) = size {
-        println!("Your terminal is {} cols wide and {} lines tall", w, h);
-    } else {
-        println!("Unable to get terminal size");
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  other failure
+  2  invalid arguments
+  3  the filters matched nothing
+  4  partition or node not found
+  5  Slurm output or input file could not be parsed
+  6  scontrol (or another Slurm command) is not installed
+  7  the Slurm controller is unreachable
+  8  a Slurm command failed for another reason
+  9  an input file could not be read";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::from(exit_code::SUCCESS),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            let code = error
+                .downcast_ref::<SlurmToolError>()
+                .map_or(exit_code::FAILURE, SlurmToolError::exit_code);
+            ExitCode::from(code)
+        }
     }
+}
 
+fn run() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    let source = cli.source();
+    let output = cli.output;
 
     match cli.command {
-        Commands::Nodes { partition, limit, debug } => {
-            display_partition_nodes(&partition, limit, debug)?;
+        Commands::Nodes(args) => {
+            display_partition_nodes(source.as_ref(), &args, output)?;
+        }
+        Commands::Node { name } => {
+            display_node(source.as_ref(), &name, output)?;
+        }
+        Commands::GroupNodes { partition, node_filter } => {
+            group_partition_nodes(source.as_ref(), &partition, node_filter.filter.as_ref(), output)?;
+        }
+        Commands::Gres { partition, name, node_filter } => {
+            display_gres_inventory(
+                source.as_ref(),
+                partition.as_deref(),
+                &name,
+                node_filter.filter.as_ref(),
+                output.format,
+            )?;
+        }
+        Commands::Jobs(args) => {
+            display_jobs(source.as_ref(), &args, output)?;
+        }
+        Commands::History(args) => {
+            display_history(source.as_ref(), &args, output)?;
+        }
+        Commands::Efficiency(mut args) => {
+            args.selection.jobs.extend(args.job.take());
+            display_efficiency(source.as_ref(), &args, output)?;
+        }
+        Commands::Partitions { all } => {
+            display_partitions(source.as_ref(), all, output)?;
+        }
+        Commands::Qos { partition, all } => {
+            display_qos_limits(source.as_ref(), partition.as_deref(), all, output)?;
         }
-        Commands::GroupNodes { partition } => {
-            group_partition_nodes(&partition)?;
+        Commands::Reservations { node } => {
+            display_reservations(source.as_ref(), node.as_deref(), output)?;
+        }
+        Commands::Tui { interval } => {
+            let interval = (interval > 0).then(|| std::time::Duration::from_secs(interval));
+            slurmtool::tui::run(source.as_ref(), output.units, interval)?;
+        }
+        Commands::Fit(args) => {
+            display_fit(source.as_ref(), args, output)?;
         }
     }
     Ok(())
 }
-/// Displays the nodes in the specified partition
+/// Displays the nodes in the specified partition, one table row per node
 fn display_partition_nodes(
-    partition_name: &str,
-    limit: Option<usize>,
-    debug: bool
+    source: &dyn SlurmSource,
+    args: &NodesArgs,
+    output: OutputOptions
 ) -> Result<(), Box<dyn Error>> {
-    let node_map: NodeMap = NodeMap::build()?;
-    let partition_map: PartitionMap = PartitionMap::build()?;
+    let columns: Vec<&str> = args.columns.iter().map(String::as_str).collect();
+    table::check_columns(columns.iter().copied(), NODE_COLUMNS)?;
+    table::check_columns(args.sort.iter().map(|key| key.column.as_str()), NODE_COLUMNS)?;
 
-    let partition = partition_map
-        .get(partition_name)
-        .ok_or_else(|| format!("Partition '{}' not found", partition_name))?;
+    let mut node_map: NodeMap = NodeMap::build(source)?;
+    let partition_map: PartitionMap = PartitionMap::build(source)?;
+
+    let partition = partition_map.lookup(&args.partition)?;
+    let job_columns = ["jobs", "users"];
+    let needs_jobs = args.show_jobs
+        || columns.iter().any(|column| job_columns.contains(column))
+        || args.sort.iter().any(|key| job_columns.contains(&key.column.as_str()));
+    if needs_jobs {
+        node_map.attach_jobs(&JobMap::build(source)?);
+    }
+    let now = Local::now();
+    let needs_reservations = args.show_reservations
+        || columns.contains(&"next_reservation")
+        || args.sort.iter().any(|key| key.column == "next_reservation");
+    if needs_reservations {
+        node_map.attach_reservations(&ReservationMap::build(source)?, &now);
+    }
+
+    let mut nodes: Vec<&Node> = partition.nodes
+        .iter()
+        .filter_map(|name| node_map.get(name))
+        .filter(|node| {
+            args.state
+                .as_deref()
+                .is_none_or(|wanted| node.state.as_ref().is_some_and(|s| s.matches(wanted)))
+        })
+        .filter(|node| {
+            args.idle_since.is_none_or(|min_idle| node.idle_for(&now).is_some_and(|idle| idle >= min_idle))
+        })
+        .filter(|node| args.node_filter.filter.as_ref().is_none_or(|filter| filter.matches(node, &now)))
+        .collect();
+    if nodes.is_empty() && (args.state.is_some() || args.idle_since.is_some() || args.node_filter.filter.is_some()) {
+        return Err(SlurmToolError::NoMatch("nodes").into());
+    }
+    nodes.sort_by(|a, b| table::compare_by(*a, *b, &args.sort, |node, column| node.value(column, &now)));
+
+    let nodes_to_display = args.limit.unwrap_or(nodes.len()).min(nodes.len());
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &nodes[..nodes_to_display])?;
+        return Ok(());
+    }
+    if args.debug {
+        for node in nodes.iter().take(nodes_to_display) {
+            println!("{:#?}", node);
+        }
+        return Ok(());
+    }
 
-    let nodes_to_display = limit.unwrap_or(partition.nodes.len());
     println!(
-        "Partition: {} (showing {} of {} nodes)\n",
+        "Partition: {} (max time {}, showing {} of {} nodes)\n",
         partition.name,
+        partition.max_time.unwrap_or(SlurmDuration::Unlimited),
         nodes_to_display,
-        partition.nodes.len()
+        nodes.len()
     );
 
-    for node in partition.nodes.iter().take(nodes_to_display) {
-        let node = node_map.get(node).unwrap();
-        if debug {
-            println!("{:#?}", node);
+    nodes.truncate(nodes_to_display);
+    let mut table = Table::new(&columns);
+    for node in &nodes {
+        table.push(columns.iter().map(|column| node.cell(column, &now)).collect());
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    if args.show_jobs {
+        println!();
+        node_jobs_table(&nodes).write(&mut stdout, output.units, TerminalSize::table_width())?;
+    }
+    if args.show_reservations {
+        println!();
+        let table = idle_node_reservations_table(&nodes, &now);
+        if table.rows.is_empty() {
+            println!("No upcoming reservations on idle nodes");
         } else {
-            println!(
-                "Node: {:<16}\n\tCPUTotal: {:<4}\n\tMEMORY: {}\n\n",
-                node.name,
-                node.pretty_cpu(),
-                node.pretty_memory("GB")
+            println!("Upcoming reservations on idle nodes:");
+            table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// One row per upcoming reservation on each of `nodes` that is idle now, soonest first
+fn idle_node_reservations_table(nodes: &[&Node], now: &DateTime<Local>) -> Table {
+    let mut rows: Vec<(&Node, &NodeReservation)> = nodes
+        .iter()
+        .filter(|node| node.state.as_ref().is_some_and(|state| state.matches("idle")))
+        .flat_map(|node| {
+            node.reservations
+                .iter()
+                .filter(|reservation| reservation.starts_in(now).is_some())
+                .map(move |reservation| (*node, reservation))
+        })
+        .collect();
+    rows.sort_by_key(|(_, reservation)| reservation.start_time);
+
+    let mut table = Table::new(&["node", "reservation", "starts_in", "start_time", "end_time", "flags"]);
+    for (node, reservation) in rows {
+        let time = |time: Option<DateTime<Local>>| {
+            Cell::from(Value::from_option(time, |t| Value::Text(timestamp::format_timestamp(&t))))
+        };
+        let name = Value::Text(reservation.name.clone());
+        table.push(vec![
+            Value::Text(node.name.clone()).into(),
+            if reservation.is_maintenance() { Cell::colored(name, termcolor::Color::Magenta) } else { name.into() },
+            Value::from_option(reservation.starts_in(now), Value::Duration).into(),
+            time(reservation.start_time),
+            time(reservation.end_time),
+            Value::Text(reservation.flags.join(",")).into(),
+        ]);
+    }
+    table
+}
+
+/// One row per job on each of `nodes`, with the job's share of that node
+fn node_jobs_table(nodes: &[&Node]) -> Table {
+    let mut table = Table::new(NODE_JOB_COLUMNS);
+    for node in nodes {
+        for job in &node.jobs {
+            table.push(
+                NODE_JOB_COLUMNS.iter()
+                    .map(|column| match *column {
+                        "node" => Cell::from(Value::Text(node.name.clone())),
+                        "state" => match &job.state {
+                            Some(state) => Cell::colored(job.value(column), state.color()),
+                            None => Cell::from(job.value(column)),
+                        },
+                        _ => Cell::from(job.value(column)),
+                    })
+                    .collect()
             );
         }
     }
+    table
+}
+
+/// Prints every field of one node followed by the jobs running on it
+fn display_node(source: &dyn SlurmSource, name: &str, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let mut node_map: NodeMap = NodeMap::build(source)?;
+    node_map.attach_jobs(&JobMap::build(source)?);
+    let node = node_map.lookup(name)?;
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &[node])?;
+        return Ok(());
+    }
+
+    print_fields(&node.fields(output.units));
+
+    println!();
+    if node.jobs.is_empty() {
+        println!("No jobs running on {}", node.name);
+    } else {
+        let mut stdout = StandardStream::stdout(color_choice());
+        node_jobs_table(&[node]).write(&mut stdout, output.units, TerminalSize::table_width())?;
+    }
 
     Ok(())
 }
 
+/// Prints `field  value` lines with the values aligned.
+fn print_fields(fields: &[(String, String)]) {
+    let width = fields.iter().map(|(field, _)| field.len()).max().unwrap_or(0);
+    for (field, value) in fields {
+        println!("{:<width$}  {}", field, value, width = width);
+    }
+}
+
+/// Only colour output when stdout is a terminal.
+fn color_choice() -> ColorChoice {
+    if std::io::stdout().is_terminal() { ColorChoice::Auto } else { ColorChoice::Never }
+}
+
 /// Groups nodes in the specified partition by total_cpu and real_memory
-fn group_partition_nodes(partition_name: &str) -> Result<(), Box<dyn Error>> {
-    let node_map: NodeMap = NodeMap::build()?;
-    let partition_map: PartitionMap = PartitionMap::build()?;
+fn group_partition_nodes(
+    source: &dyn SlurmSource,
+    partition_name: &str,
+    filter: Option<&NodeFilter>,
+    output: OutputOptions
+) -> Result<(), Box<dyn Error>> {
+    let node_map: NodeMap = NodeMap::build(source)?;
+    let partition_map: PartitionMap = PartitionMap::build(source)?;
 
-    let partition = partition_map
-        .get(partition_name)
-        .ok_or_else(|| format!("Partition '{}' not found", partition_name))?;
+    let partition = partition_map.lookup(partition_name)?;
 
+    let now = Local::now();
     let mut groups: std::collections::BTreeMap<
-        (u32, u32),
+        (u32, Memory),
         Vec<String>
     > = std::collections::BTreeMap::new();
     for node_name in &partition.nodes {
         if let Some(node) = node_map.get(node_name) {
+            if filter.is_some_and(|filter| !filter.matches(node, &now)) {
+                continue;
+            }
             let total_cpu = node.cpu_total.unwrap_or(0);
-            let real_memory_gb = node.real_memory.expect("Real memory missing").as_gb();
-            groups.entry((total_cpu, real_memory_gb)).or_default().push(node.name.clone());
+            let real_memory = node.real_memory.unwrap_or_default();
+            groups.entry((total_cpu, real_memory)).or_default().push(node.name.clone());
         }
     }
+    if groups.is_empty() && filter.is_some() {
+        return Err(SlurmToolError::NoMatch("nodes").into());
+    }
 
-    for ((total_cpu, real_memory_gb), nodes) in groups {
+    let groups: Vec<NodeGroup> = groups
+        .into_iter()
+        .map(|((cpus, real_memory), nodes)| NodeGroup {
+            partition: partition.name.clone(),
+            cpus,
+            real_memory,
+            node_count: nodes.len(),
+            nodes: hostlist::compress(&nodes),
+        })
+        .collect();
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &groups)?;
+        return Ok(());
+    }
+
+    for group in groups {
         println!(
-            "{} - Total CPU: {}, Real Memory: {} GB",
-            partition_name,
-            total_cpu,
-            real_memory_gb
+            "{} - Total CPU: {}, Real Memory: {}",
+            group.partition,
+            group.cpus,
+            output.units.format(group.real_memory)
         );
-        for node_name in nodes {
-            println!("\t{}", node_name);
+        println!("\t{} ({} nodes)", group.nodes, group.node_count);
+    }
+
+    Ok(())
+}
+
+/// Nodes of one partition sharing a CPU count and memory size
+#[derive(Serialize)]
+struct NodeGroup {
+    partition: String,
+    cpus: u32,
+    real_memory: Memory,
+    node_count: usize,
+    /// The nodes as a compressed hostlist
+    nodes: String,
+}
+
+/// Prints the `name` GRES inventory of each partition, one line per GRES type
+fn display_gres_inventory(
+    source: &dyn SlurmSource,
+    partition_name: Option<&str>,
+    name: &str,
+    filter: Option<&NodeFilter>,
+    format: OutputFormat
+) -> Result<(), Box<dyn Error>> {
+    let node_map: NodeMap = NodeMap::build(source)?;
+    let partition_map: PartitionMap = PartitionMap::build(source)?;
+
+    let partitions: Vec<&Partition> = match partition_name {
+        Some(partition_name) => {
+            vec![partition_map.lookup(partition_name)?]
         }
+        None => partition_map.partitions.values().collect(),
+    };
+
+    let now = Local::now();
+    let rows: Vec<GresRow> = partitions
+        .into_iter()
+        .flat_map(|partition| {
+            let nodes = partition.nodes
+                .iter()
+                .filter_map(|node| node_map.get(node))
+                .filter(|node| filter.is_none_or(|filter| filter.matches(node, &now)));
+            gres::inventory(nodes, name)
+                .into_iter()
+                .map(|(kind, totals)| GresRow { partition: partition.name.clone(), kind, totals })
+        })
+        .collect();
+    if !format.is_text() {
+        output::write_records(&mut std::io::stdout(), format, &rows)?;
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<16} {:>6} {:>6} {:>6} {:>6} {:>8}",
+        "PARTITION",
+        "TYPE",
+        "NODES",
+        "TOTAL",
+        "USED",
+        "FREE",
+        "UNAVAIL"
+    );
+    for row in rows {
+        println!(
+            "{:<16} {:<16} {:>6} {:>6} {:>6} {:>6} {:>8}",
+            row.partition,
+            row.kind,
+            row.totals.nodes,
+            row.totals.total,
+            row.totals.used,
+            row.totals.free,
+            row.totals.unavailable
+        );
     }
 
     Ok(())
 }
+
+/// One line of the GRES inventory
+#[derive(Serialize)]
+struct GresRow {
+    partition: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    totals: gres::GresTotals,
+}
+
+/// Displays the jobs matching the user, partition and state filters, one table row per job
+fn display_jobs(source: &dyn SlurmSource, args: &JobsArgs, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let columns: Vec<&str> = args.columns.iter().map(String::as_str).collect();
+    table::check_columns(columns.iter().copied(), JOB_COLUMNS)?;
+    table::check_columns(args.sort.iter().map(|key| key.column.as_str()), JOB_COLUMNS)?;
+
+    let job_map: JobMap = JobMap::build(source)?;
+    let mut jobs: Vec<&Job> = job_map.jobs
+        .values()
+        .filter(|job| job.has_user(&args.user) && job.in_partition(&args.partition) && job.has_state(&args.state))
+        .collect();
+    let filtered = !(args.user.is_empty() && args.partition.is_empty() && args.state.is_empty());
+    if jobs.is_empty() && filtered {
+        return Err(SlurmToolError::NoMatch("jobs").into());
+    }
+    jobs.sort_by(|a, b| table::compare_by(*a, *b, &args.sort, |job, column| job.value(column)));
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &jobs)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(&columns);
+    for job in jobs {
+        table.push(columns.iter().map(|column| job.cell(column)).collect());
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    Ok(())
+}
+
+/// Displays the accounting records of past jobs, one table row per job or step
+fn display_history(source: &dyn SlurmSource, args: &HistoryArgs, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let columns: Vec<&str> = args.columns.iter().map(String::as_str).collect();
+    table::check_columns(columns.iter().copied(), HISTORY_COLUMNS)?;
+    table::check_columns(args.sort.iter().map(|key| key.column.as_str()), HISTORY_COLUMNS)?;
+
+    let mut records: Vec<AccountingRecord> = args.selection.query(args.allocations).fetch(source)?;
+    if records.is_empty() {
+        return Err(SlurmToolError::NoMatch("jobs").into());
+    }
+    records.sort_by(|a, b| table::compare_by(a, b, &args.sort, |record, column| record.value(column)));
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &records)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(&columns);
+    for record in &records {
+        table.push(columns.iter().map(|column| record.cell(column)).collect());
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    Ok(())
+}
+
+/// Displays the CPU, memory and GPU efficiency of finished and running jobs: a seff-style
+/// report for a single job, otherwise a table followed by the users wasting the most
+fn display_efficiency(source: &dyn SlurmSource, args: &EfficiencyArgs, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let columns: Vec<&str> = args.columns.iter().map(String::as_str).collect();
+    table::check_columns(columns.iter().copied(), EFFICIENCY_COLUMNS)?;
+    table::check_columns(args.sort.iter().map(|key| key.column.as_str()), EFFICIENCY_COLUMNS)?;
+
+    let records = args.selection.query(false).fetch(source)?;
+    let mut jobs: Vec<JobEfficiency> = efficiency::job_efficiencies(&records);
+    if jobs.is_empty() {
+        return Err(SlurmToolError::NoMatch("jobs").into());
+    }
+    jobs.sort_by(|a, b| table::compare_by(a, b, &args.sort, |job, column| job.value(column)));
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &jobs)?;
+        return Ok(());
+    }
+
+    if let [job] = jobs.as_slice() {
+        print_fields(&job.fields(output.units));
+        return Ok(());
+    }
+
+    let mut stdout = StandardStream::stdout(color_choice());
+    let mut table = Table::new(&columns);
+    for job in &jobs {
+        table.push(columns.iter().map(|column| job.cell(column)).collect());
+    }
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    if args.top > 0 {
+        let mut offenders = Table::new(USER_EFFICIENCY_COLUMNS);
+        for user in efficiency::worst_offenders(&jobs).iter().take(args.top) {
+            offenders.push(USER_EFFICIENCY_COLUMNS.iter().map(|column| user.cell(column)).collect());
+        }
+        println!();
+        println!("Worst offenders (most requested memory unused, then most idle cores):");
+        offenders.write(&mut stdout, output.units, TerminalSize::table_width())?;
+    }
+
+    Ok(())
+}
+
+/// Prints the effective limits of every partition; limits that come from the partition QoS
+/// are coloured
+fn display_qos_limits(
+    source: &dyn SlurmSource,
+    partition: Option<&str>,
+    all: bool,
+    output: OutputOptions
+) -> Result<(), Box<dyn Error>> {
+    let partition_map: PartitionMap = PartitionMap::build(source)?;
+    let qos_map: QosMap = QosMap::build(source)?;
+
+    let partitions: Vec<&Partition> = match partition {
+        Some(name) => vec![partition_map.lookup(name)?],
+        None => partition_map.partitions.values().filter(|partition| all || !partition.hidden).collect(),
+    };
+    let limits: Vec<PartitionLimits> = partitions
+        .into_iter()
+        .map(|partition| PartitionLimits::new(partition, &qos_map))
+        .collect();
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &limits)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(LIMIT_COLUMNS);
+    for partition_limits in &limits {
+        table.push(LIMIT_COLUMNS.iter().map(|column| partition_limits.cell(column)).collect());
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+    if std::io::stdout().is_terminal() {
+        println!("\nLimits in cyan are set by the partition QoS and override the partition's own.");
+    }
+
+    Ok(())
+}
+
+/// Lists the reservations by start time, optionally only those that include `node`
+fn display_reservations(source: &dyn SlurmSource, node: Option<&str>, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let reservation_map: ReservationMap = ReservationMap::build(source)?;
+    if let Some(node) = node {
+        NodeMap::build(source)?.lookup(node)?;
+    }
+
+    let mut reservations: Vec<&Reservation> = reservation_map.reservations
+        .values()
+        .filter(|reservation| node.is_none_or(|node| reservation.has_node(node)))
+        .collect();
+    if reservations.is_empty() && node.is_some() {
+        return Err(SlurmToolError::NoMatch("reservations").into());
+    }
+    reservations.sort_by_key(|reservation| reservation.start_time);
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &reservations)?;
+        return Ok(());
+    }
+    if reservations.is_empty() {
+        println!("No reservations");
+        return Ok(());
+    }
+
+    let now = Local::now();
+    let mut table = Table::new(RESERVATION_COLUMNS);
+    for reservation in reservations {
+        table.push(RESERVATION_COLUMNS.iter().map(|column| reservation.cell(column, &now)).collect());
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    Ok(())
+}
+
+/// Prints one summary line per partition, like `sinfo -s` but with free resources; the default
+/// partition is marked with `*`
+fn display_partitions(source: &dyn SlurmSource, all: bool, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let node_map: NodeMap = NodeMap::build(source)?;
+    let partition_map: PartitionMap = PartitionMap::build(source)?;
+
+    let summaries: Vec<PartitionSummary> = partition_map.partitions
+        .values()
+        .filter(|partition| all || !partition.hidden)
+        .map(|partition| PartitionSummary::new(partition, &node_map))
+        .collect();
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &summaries)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(&[
+        "partition", "state", "default_time", "max_time", "nodes", "idle", "mixed", "alloc", "drain",
+        "down", "cpu_free", "cpu_total", "mem_free", "mem_total", "gpu_free", "gpu_total",
+    ]);
+    for summary in summaries {
+        let count = |count: u32| Cell::from(Value::Count(u64::from(count)));
+        let name = if summary.default { format!("{}*", summary.name) } else { summary.name };
+        table.push(vec![
+            Value::Text(name).into(),
+            Value::from_option(summary.state, Value::Text).into(),
+            Value::from_option(summary.default_time, Value::Duration).into(),
+            Value::from_option(summary.max_time, Value::Duration).into(),
+            count(summary.nodes.total),
+            count(summary.nodes.idle),
+            count(summary.nodes.mixed),
+            count(summary.nodes.alloc),
+            count(summary.nodes.drain),
+            count(summary.nodes.down),
+            Value::Count(summary.cpus_free).into(),
+            Value::Count(summary.cpus_total).into(),
+            Value::Memory(summary.mem_free).into(),
+            Value::Memory(summary.mem_total).into(),
+            Value::Count(summary.gpus_free).into(),
+            Value::Count(summary.gpus_total).into(),
+        ]);
+    }
+    let mut stdout = StandardStream::stdout(color_choice());
+    table.write(&mut stdout, output.units, TerminalSize::table_width())?;
+
+    Ok(())
+}
+
+/// Prints which partitions can run the requested job now, which could run it eventually and
+/// why the others reject it
+fn display_fit(source: &dyn SlurmSource, args: FitArgs, output: OutputOptions) -> Result<(), Box<dyn Error>> {
+    let node_map: NodeMap = NodeMap::build(source)?;
+    let mut partition_map: PartitionMap = PartitionMap::build(source)?;
+    if let Some(partition_name) = &args.partition {
+        partition_map.lookup(partition_name)?;
+        partition_map.partitions.retain(|name, _| name == partition_name);
+    }
+
+    let request = JobRequest {
+        cpus: args.cpus,
+        mem: args.mem,
+        gres: args.gres,
+        time: args.time,
+        nodes: args.nodes,
+        account: args.account,
+        qos: args.qos,
+    };
+    let mut fits = fit::fit(&request, &partition_map, &node_map, output.units);
+    fits.sort_by_key(|fit| fit.status);
+
+    if !output.format.is_text() {
+        output::write_records(&mut std::io::stdout(), output.format, &fits)?;
+    } else {
+        print_fit_section("Can run now", &fits, FitStatus::Now, |fit| fit.nodes_now.clone());
+        print_fit_section("Could run eventually", &fits, FitStatus::Eventually, |fit| {
+            format!("{} ({})", fit.nodes_eventually, fit.reasons.join("; "))
+        });
+        print_fit_section("Rejected", &fits, FitStatus::Rejected, |fit| fit.reasons.join("; "));
+    }
+
+    if fits.iter().all(|fit| fit.status == FitStatus::Rejected) {
+        return Err(SlurmToolError::NoMatch("partitions").into());
+    }
+    Ok(())
+}
+
+fn print_fit_section(
+    title: &str,
+    fits: &[PartitionFit],
+    status: FitStatus,
+    detail: impl Fn(&PartitionFit) -> String
+) {
+    let fits: Vec<&PartitionFit> = fits.iter().filter(|fit| fit.status == status).collect();
+    if fits.is_empty() {
+        return;
+    }
+    let width = fits.iter().map(|fit| fit.partition.len()).max().unwrap_or(0);
+    println!("{}:", title);
+    for fit in fits {
+        println!("  {:<width$}  {}", fit.partition, detail(fit), width = width);
+    }
+}
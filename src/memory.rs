@@ -0,0 +1,408 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+/// A binary size unit as Slurm uses them: `M` is 1024 * 1024 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryUnit {
+    B,
+    K,
+    M,
+    G,
+    T,
+    P,
+}
+
+impl MemoryUnit {
+    pub const ALL: [MemoryUnit; 6] = [
+        MemoryUnit::B,
+        MemoryUnit::K,
+        MemoryUnit::M,
+        MemoryUnit::G,
+        MemoryUnit::T,
+        MemoryUnit::P,
+    ];
+
+    /// Bytes in one unit.
+    pub fn bytes(&self) -> u64 {
+        1 << (10 * *self as u32)
+    }
+
+    /// The single letter suffix Slurm uses, `B` for plain bytes.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            MemoryUnit::B => "B",
+            MemoryUnit::K => "K",
+            MemoryUnit::M => "M",
+            MemoryUnit::G => "G",
+            MemoryUnit::T => "T",
+            MemoryUnit::P => "P",
+        }
+    }
+
+    /// Parses `K`, `KB`, `KiB` (any case) and so on. All of them mean powers of 1024, as in
+    /// Slurm.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        let suffix = suffix.trim().to_ascii_uppercase();
+        let letter = suffix
+            .strip_suffix("IB")
+            .or_else(|| suffix.strip_suffix('B').filter(|s| !s.is_empty()))
+            .unwrap_or(&suffix);
+        match letter {
+            "B" => Some(MemoryUnit::B),
+            "K" => Some(MemoryUnit::K),
+            "M" => Some(MemoryUnit::M),
+            "G" => Some(MemoryUnit::G),
+            "T" => Some(MemoryUnit::T),
+            "P" => Some(MemoryUnit::P),
+            _ => None,
+        }
+    }
+
+    /// The largest unit in which `bytes` is at least one.
+    pub fn auto(bytes: u64) -> Self {
+        MemoryUnit::ALL
+            .into_iter()
+            .rev()
+            .find(|unit| bytes >= unit.bytes())
+            .unwrap_or(MemoryUnit::B)
+    }
+}
+
+/// An amount of memory with byte precision.
+///
+/// Slurm reports memory in megabytes (`RealMemory=256000`), so `Memory::new` takes
+/// megabytes; sizes with a unit (`500G`, `62.50G`, `2T`) parse with `str::parse`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Memory {
+    bytes: u64,
+}
+
+impl Memory {
+    /// Memory from a number of megabytes, Slurm's default unit.
+    pub const fn new(megabytes: u64) -> Self {
+        Self::from_bytes(megabytes.saturating_mul(1024 * 1024))
+    }
+
+    pub const fn from_bytes(bytes: u64) -> Self {
+        Self { bytes }
+    }
+
+    pub fn from_unit(amount: u64, unit: MemoryUnit) -> Self {
+        Self::from_bytes(amount.saturating_mul(unit.bytes()))
+    }
+
+    pub fn as_bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn as_kb(&self) -> u64 {
+        self.bytes / MemoryUnit::K.bytes()
+    }
+
+    pub fn as_mb(&self) -> u64 {
+        self.bytes / MemoryUnit::M.bytes()
+    }
+
+    pub fn as_gb(&self) -> f64 {
+        self.in_unit(MemoryUnit::G)
+    }
+
+    pub fn as_tb(&self) -> f64 {
+        self.in_unit(MemoryUnit::T)
+    }
+
+    /// The amount expressed in `unit`, with fractions.
+    pub fn in_unit(&self, unit: MemoryUnit) -> f64 {
+        self.bytes as f64 / unit.bytes() as f64
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.bytes == 0
+    }
+
+    pub fn checked_sub(self, other: Memory) -> Option<Memory> {
+        self.bytes.checked_sub(other.bytes).map(Memory::from_bytes)
+    }
+
+    pub fn saturating_sub(self, other: Memory) -> Memory {
+        Memory::from_bytes(self.bytes.saturating_sub(other.bytes))
+    }
+
+    /// Formats in `unit` with up to two decimals, e.g. `62.5G`.
+    pub fn format_in(&self, unit: MemoryUnit) -> String {
+        format!("{}{}", format_amount(self.in_unit(unit)), unit.suffix())
+    }
+}
+
+/// Up to two decimals without trailing zeros: `500`, `62.5`, `1.95`.
+pub(crate) fn format_amount(amount: f64) -> String {
+    let formatted = format!("{:.2}", amount);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Whether rendered sizes use powers of 1024 (`GiB`) or powers of 1000 (`GB`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    #[default]
+    Iec,
+    Si,
+}
+
+impl UnitSystem {
+    fn base(&self) -> u64 {
+        match self {
+            UnitSystem::Iec => 1024,
+            UnitSystem::Si => 1000,
+        }
+    }
+}
+
+/// How memory is rendered for people, as chosen with `--units`.
+///
+/// Slurm itself always means powers of 1024, so `DisplayUnits` only affects output: `gib`
+/// renders 1024 MB as `1 GiB` while `gb` renders the same amount as `1.07 GB`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayUnits {
+    /// A fixed unit, or `None` to pick the largest unit that keeps the amount at least one.
+    pub unit: Option<MemoryUnit>,
+    pub system: UnitSystem,
+}
+
+impl DisplayUnits {
+    pub const NAMES: [&'static str; 10] =
+        ["auto", "si", "kib", "mib", "gib", "tib", "kb", "mb", "gb", "tb"];
+
+    pub fn fixed(unit: MemoryUnit, system: UnitSystem) -> Self {
+        Self { unit: Some(unit), system }
+    }
+
+    /// Renders `memory` with a space and an explicit unit: `62.5 GiB`, `65.54 GB`.
+    pub fn format(&self, memory: Memory) -> String {
+        let base = self.system.base() as f64;
+        let bytes = memory.as_bytes() as f64;
+        let unit = self.unit.unwrap_or_else(|| {
+            MemoryUnit::ALL
+                .into_iter()
+                .rev()
+                .find(|unit| bytes >= base.powi(*unit as i32))
+                .unwrap_or(MemoryUnit::B)
+        });
+
+        let amount = bytes / base.powi(unit as i32);
+        let suffix = match (unit, self.system) {
+            (MemoryUnit::B, _) => "B".to_string(),
+            (unit, UnitSystem::Iec) => format!("{}iB", unit.suffix()),
+            (MemoryUnit::K, UnitSystem::Si) => "kB".to_string(),
+            (unit, UnitSystem::Si) => format!("{}B", unit.suffix()),
+        };
+        format!("{} {}", format_amount(amount), suffix)
+    }
+
+    /// Like `format`, with `N/A` for a missing value.
+    pub fn format_optional(&self, memory: Option<Memory>) -> String {
+        memory.map_or_else(|| "N/A".to_string(), |memory| self.format(memory))
+    }
+}
+
+impl FromStr for DisplayUnits {
+    type Err = String;
+
+    /// Parses `auto` (IEC), `si` (automatic SI), `kib`..`tib` or `kb`..`tb`, in any case.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let lower = value.trim().to_ascii_lowercase();
+        let units = match lower.as_str() {
+            "auto" | "iec" => DisplayUnits::default(),
+            "si" => DisplayUnits { unit: None, system: UnitSystem::Si },
+            other => {
+                let (letter, system) = match other.strip_suffix("ib") {
+                    Some(letter) => (letter, UnitSystem::Iec),
+                    None => (other.strip_suffix('b').unwrap_or(""), UnitSystem::Si),
+                };
+                let unit = match letter {
+                    "k" => MemoryUnit::K,
+                    "m" => MemoryUnit::M,
+                    "g" => MemoryUnit::G,
+                    "t" => MemoryUnit::T,
+                    _ => {
+                        return Err(format!(
+                            "Invalid units '{}', expected one of: {}",
+                            value,
+                            DisplayUnits::NAMES.join(", ")
+                        ))
+                    }
+                };
+                DisplayUnits::fixed(unit, system)
+            }
+        };
+        Ok(units)
+    }
+}
+
+impl FromStr for Memory {
+    type Err = String;
+
+    /// Parses `500G`, `62.50G`, `64000M`, `2T`, `1.5TiB` or a bare number of megabytes.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let split = value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(value.len());
+        let (number, suffix) = value.split_at(split);
+
+        let unit = if suffix.is_empty() {
+            MemoryUnit::M
+        } else {
+            MemoryUnit::from_suffix(suffix).ok_or_else(|| format!("Invalid memory unit in '{}'", value))?
+        };
+        let amount: f64 = number
+            .parse()
+            .map_err(|_| format!("Invalid memory size '{}'", value))?;
+
+        let bytes = amount * unit.bytes() as f64;
+        if !bytes.is_finite() || bytes > u64::MAX as f64 {
+            return Err(format!("Memory size '{}' is too large", value));
+        }
+        Ok(Memory::from_bytes(bytes.round() as u64))
+    }
+}
+
+impl fmt::Display for Memory {
+    /// Formats in the largest unit that keeps the amount at least one, e.g. `500G` or `1.95T`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let formatted = self.format_in(MemoryUnit::auto(self.bytes));
+        f.pad(&formatted)
+    }
+}
+
+impl Add for Memory {
+    type Output = Memory;
+
+    fn add(self, other: Memory) -> Memory {
+        Memory::from_bytes(self.bytes.saturating_add(other.bytes))
+    }
+}
+
+impl AddAssign for Memory {
+    fn add_assign(&mut self, other: Memory) {
+        *self = *self + other;
+    }
+}
+
+/// Subtraction saturates at zero; memory cannot be negative.
+impl Sub for Memory {
+    type Output = Memory;
+
+    fn sub(self, other: Memory) -> Memory {
+        self.saturating_sub(other)
+    }
+}
+
+impl Mul<u64> for Memory {
+    type Output = Memory;
+
+    fn mul(self, factor: u64) -> Memory {
+        Memory::from_bytes(self.bytes.saturating_mul(factor))
+    }
+}
+
+impl Sum for Memory {
+    fn sum<I: Iterator<Item = Memory>>(iter: I) -> Memory {
+        iter.fold(Memory::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Memory> for Memory {
+    fn sum<I: Iterator<Item = &'a Memory>>(iter: I) -> Memory {
+        iter.copied().sum()
+    }
+}
+
+/// Serializes as a whole number of megabytes, the unit Slurm itself reports.
+impl Serialize for Memory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_mb())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory() {
+        let test_cases = vec![
+            ("500G", Memory::new(500 * 1024)),
+            ("64000M", Memory::new(64000)),
+            ("64000", Memory::new(64000)),
+            ("2T", Memory::new(2 * 1024 * 1024)),
+            ("62.50G", Memory::new(64000)),
+            ("1.5TiB", Memory::from_unit(1536, MemoryUnit::G)),
+            ("512KB", Memory::from_bytes(512 * 1024)),
+            ("100B", Memory::from_bytes(100)),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(input.parse::<Memory>().unwrap(), expected, "input: {}", input);
+        }
+
+        for input in ["", "G", "12X", "1..2G", "-5G"] {
+            assert!(input.parse::<Memory>().is_err(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_large_memory() {
+        // 6 TB does not fit the old u32 kilobyte conversion
+        let memory: Memory = "6T".parse().unwrap();
+        assert_eq!(memory.as_kb(), 6 * 1024 * 1024 * 1024);
+        assert_eq!(memory.as_mb(), 6 * 1024 * 1024);
+        assert_eq!(memory.as_tb(), 6.0);
+    }
+
+    #[test]
+    fn test_format_memory() {
+        assert_eq!(Memory::new(500 * 1024).to_string(), "500G");
+        assert_eq!(Memory::new(64000).to_string(), "62.5G");
+        assert_eq!(Memory::new(2_000_000).to_string(), "1.91T");
+        assert_eq!(Memory::new(0).to_string(), "0B");
+        assert_eq!(Memory::new(64000).format_in(MemoryUnit::M), "64000M");
+        assert_eq!(format!("{:>6}", Memory::new(1024)), "    1G");
+    }
+
+    #[test]
+    fn test_memory_arithmetic() {
+        let real = Memory::new(256000);
+        let alloc = Memory::new(64000);
+
+        assert_eq!(real - alloc, Memory::new(192000));
+        assert_eq!(alloc - real, Memory::default());
+        assert_eq!(alloc.checked_sub(real), None);
+        assert_eq!(alloc + alloc, Memory::new(128000));
+        assert_eq!(alloc * 4, real);
+        assert_eq!([real, alloc].iter().sum::<Memory>(), Memory::new(320000));
+    }
+
+    #[test]
+    fn test_display_units() {
+        let memory = Memory::new(64000);
+        let units = |name: &str| name.parse::<DisplayUnits>().unwrap();
+
+        assert_eq!(units("auto").format(memory), "62.5 GiB");
+        assert_eq!(units("si").format(memory), "67.11 GB");
+        assert_eq!(units("GiB").format(memory), "62.5 GiB");
+        assert_eq!(units("gb").format(memory), "67.11 GB");
+        assert_eq!(units("mib").format(memory), "64000 MiB");
+        assert_eq!(units("tib").format(Memory::new(6 * 1024 * 1024)), "6 TiB");
+        assert_eq!(units("kb").format(Memory::from_bytes(1000)), "1 kB");
+        assert_eq!(units("auto").format(Memory::default()), "0 B");
+        assert_eq!(units("auto").format_optional(None), "N/A");
+
+        for input in ["", "g", "gigabytes", "pib"] {
+            assert!(input.parse::<DisplayUnits>().is_err(), "input: {}", input);
+        }
+    }
+}
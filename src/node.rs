@@ -1,28 +1,47 @@
-use std::error::Error;
 use std::collections::BTreeMap;
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Memory {
-	pub megabytes: u32,
-}
-
-impl Memory {
-	pub fn new(megabytes: u32) -> Self {
-		Self { megabytes }
-	}
-
-	pub fn as_kb(&self) -> u32 {
-		self.megabytes * 1024
-	}
-
-	pub fn as_mb(&self) -> u32 {
-		self.megabytes as u32
-	}
-
-    pub fn as_gb(&self) -> u32 {
-        (self.megabytes as f64 / 1024.0).round() as u32
-    }
-}
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::gres::{self, Gres};
+use crate::job::{JobMap, NodeJob};
+use crate::json;
+pub use crate::memory::{DisplayUnits, Memory};
+use crate::node_state::{BaseState, NodeState};
+use crate::reservation::{NodeReservation, ReservationMap};
+use crate::timestamp::{self, parse_timestamp};
+use crate::tres::Tres;
+use crate::oneliner;
+use crate::source::{Entity, SlurmSource};
+use crate::table::{Cell, Value};
+
+/// Columns `Node::value` knows, for `nodes --columns` and `--sort`.
+pub const NODE_COLUMNS: &[&str] = &[
+    "name", "state", "partitions", "cpu_total", "cpu_alloc", "cpu_free", "cpu_load", "mem_total",
+    "mem_alloc", "mem_free", "gres", "gres_used", "gres_free", "features", "uptime", "idle_for",
+    "reason", "jobs", "users", "next_reservation",
+];
+
+/// Node fields serialized as megabytes, which `Node::fields` shows in display units.
+const MEMORY_FIELDS: [&str; 3] = ["real_memory", "allocated_memory", "mem_spec_limit"];
+
+/// Every key `scontrol show node --oneliner` may print, including the ones `Node` ignores.
+///
+/// The oneliner tokenizer only starts a new field at one of these keys, so a key missing here
+/// would be glued onto the value before it.
+pub const NODE_KEYS: &[&str] = &[
+    "NodeName", "Arch", "CoresPerSocket", "CPUAlloc", "CPUEfctv", "CPUTot", "CPULoad",
+    "AvailableFeatures", "ActiveFeatures", "Gres", "GresDrain", "GresUsed", "NodeAddr",
+    "NodeHostName", "Port", "Version", "OS", "RealMemory", "AllocMem", "FreeMem", "Sockets",
+    "Boards", "CoreSpecCount", "CPUSpecList", "MemSpecLimit", "State", "ThreadsPerCore",
+    "TmpDisk", "Weight", "Owner", "MCS_label", "Partitions", "BootTime", "SlurmdStartTime",
+    "LastBusyTime", "ResumeAfterTime", "CfgTRES", "AllocTRES", "CapWatts", "CurrentWatts",
+    "AveWatts", "LowestJoules", "ConsumedJoules", "ExtSensorsJoules", "ExtSensorsWatts",
+    "ExtSensorsTemp", "PowerConsumption", "Topology", "NextState", "InstanceId",
+    "InstanceType", "ReservationName", "Reason", "Comment", "Extra", "CloudInstance",
+];
 
 #[derive(Debug, Default)]
 pub struct NodeMap {
@@ -30,14 +49,14 @@ pub struct NodeMap {
 }
 
 impl NodeMap {
-    pub fn build() -> Result<Self, Box<dyn Error>> {
-        let nodes = Node::fetch_and_parse_nodes()?;
+    pub fn build(source: &dyn SlurmSource) -> Result<Self, SlurmToolError> {
+        let nodes = Node::fetch_and_parse_nodes(source)?;
         let mut node_map = NodeMap::default();
 
         for node in nodes {
             // make sure the node name is unique
             if node_map.nodes.contains_key(&node.name) {
-                return Err(format!("Duplicate node name: {}", node.name).into());
+                return Err(SlurmToolError::Duplicate { kind: "node", name: node.name });
             }
 
             node_map.nodes.insert(node.name.clone(), node);
@@ -49,10 +68,48 @@ impl NodeMap {
     pub fn get(&self, name: &str) -> Option<&Node> {
         self.nodes.get(name)
     }
-}
 
+    /// Like `get`, but a missing node is an error.
+    pub fn lookup(&self, name: &str) -> Result<&Node, SlurmToolError> {
+        self.get(name).ok_or_else(|| SlurmToolError::UnknownNode(name.to_string()))
+    }
 
-#[derive(Debug, Default)]
+    /// Fills in each node's `jobs` with the active jobs of `jobs` allocated on it, replacing
+    /// any earlier join. Nodes a job lists but the map does not know are skipped.
+    pub fn attach_jobs(&mut self, jobs: &JobMap) {
+        for node in self.nodes.values_mut() {
+            node.jobs.clear();
+        }
+        for job in jobs.jobs.values().filter(|job| job.state.as_ref().is_some_and(|s| s.is_active())) {
+            let share = job.node_share();
+            for name in &job.nodes {
+                if let Some(node) = self.nodes.get_mut(name) {
+                    node.jobs.push(share.clone());
+                }
+            }
+        }
+    }
+
+    /// Fills in each node's `reservations` with the reservations of `reservations` that
+    /// include it and have not ended by `now`, replacing any earlier join.
+    pub fn attach_reservations(&mut self, reservations: &ReservationMap, now: &DateTime<Local>) {
+        for node in self.nodes.values_mut() {
+            node.reservations.clear();
+        }
+        for reservation in reservations.reservations.values().filter(|r| !r.has_ended(now)) {
+            for name in &reservation.nodes {
+                if let Some(node) = self.nodes.get_mut(name) {
+                    node.reservations.push(reservation.node_share());
+                }
+            }
+        }
+        for node in self.nodes.values_mut() {
+            node.reservations.sort_by_key(|reservation| reservation.start_time);
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct Node {
     pub name: String,
     pub arch: Option<String>,
@@ -63,7 +120,8 @@ pub struct Node {
     pub cpu_load: Option<f64>,
     pub available_features: Vec<String>,
     pub active_features: Vec<String>,
-    pub gres: Option<String>,
+    pub gres: Vec<Gres>,
+    pub gres_used: Vec<Gres>,
     pub addr: Option<String>,
     pub hostname: Option<String>,
     pub version: Option<String>,
@@ -72,20 +130,24 @@ pub struct Node {
 	pub allocated_memory: Option<Memory>,
     pub sockets: Option<u32>,
     pub boards: Option<u32>,
-    pub mem_spec_limit: Option<u32>,
-    pub state: Option<String>,
+    pub mem_spec_limit: Option<Memory>,
+    pub state: Option<NodeState>,
     pub threads_per_core: Option<u32>,
     pub tmp_disk: Option<u32>,
     pub weight: Option<u32>,
     pub owner: Option<String>,
     pub mcs_label: Option<String>,
     pub partitions: Vec<String>,
-    pub boot_time: Option<String>,
-    pub slurmd_start_time: Option<String>,
-    pub last_busy_time: Option<String>,
-    pub resume_after_time: Option<String>,
-    pub cfg_tres: Option<String>,
-    pub alloc_tres: Option<String>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub boot_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub slurmd_start_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub last_busy_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub resume_after_time: Option<DateTime<Local>>,
+    pub cfg_tres: Option<Tres>,
+    pub alloc_tres: Option<Tres>,
     pub cap_watts: Option<String>,
     pub current_watts: Option<u32>,
     pub ave_watts: Option<u32>,
@@ -94,6 +156,13 @@ pub struct Node {
     pub ext_sensors_temp: Option<String>,
     pub reservation_name: Option<String>,
     pub reason: Option<String>,
+    /// Active jobs on this node, once joined with `NodeMap::attach_jobs`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub jobs: Vec<NodeJob>,
+    /// Current and upcoming reservations of this node by start time, once joined with
+    /// `NodeMap::attach_reservations`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reservations: Vec<NodeReservation>,
 }
 impl Node {
     pub fn from_fields(fields: &[(&str, &str)]) -> Self {
@@ -114,18 +183,19 @@ impl Node {
                 "ActiveFeatures" => {
                     node.active_features = value.split(',').map(String::from).collect();
                 }
-                "Gres" => node.gres = Some(value.to_string()),
+                "Gres" => node.gres = gres::parse_gres_list(value).unwrap_or_default(),
+                "GresUsed" => node.gres_used = gres::parse_gres_list(value).unwrap_or_default(),
                 "NodeAddr" => node.addr = Some(value.to_string()),
                 "NodeHostName" => node.hostname = Some(value.to_string()),
                 "Version" => node.version = Some(value.to_string()),
                 "OS" => node.os = Some(value.to_string()),
-                "RealMemory" => node.real_memory = value.parse().ok().map(Memory::new),
-                "AllocMem" => node.allocated_memory = value.parse().ok().map(Memory::new),
+                "RealMemory" => node.real_memory = value.parse().ok(),
+                "AllocMem" => node.allocated_memory = value.parse().ok(),
                 //"FreeMem" => node.free_memory = value.parse().ok(),  // doesnt make sense from scontrol output ...
                 "Sockets" => node.sockets = value.parse().ok(),
                 "Boards" => node.boards = value.parse().ok(),
                 "MemSpecLimit" => node.mem_spec_limit = value.parse().ok(),
-                "State" => node.state = Some(value.to_string()),
+                "State" => node.state = value.parse().ok(),
                 "ThreadsPerCore" => node.threads_per_core = value.parse().ok(),
                 "TmpDisk" => node.tmp_disk = value.parse().ok(),
                 "Weight" => node.weight = value.parse().ok(),
@@ -134,12 +204,12 @@ impl Node {
                 "Partitions" => {
                     node.partitions = value.split(',').map(String::from).collect();
                 }
-                "BootTime" => node.boot_time = Some(value.to_string()),
-                "SlurmdStartTime" => node.slurmd_start_time = Some(value.to_string()),
-                "LastBusyTime" => node.last_busy_time = Some(value.to_string()),
-                "ResumeAfterTime" => node.resume_after_time = Some(value.to_string()),
-                "CfgTRES" => node.cfg_tres = Some(value.to_string()),
-                "AllocTRES" => node.alloc_tres = Some(value.to_string()),
+                "BootTime" => node.boot_time = parse_timestamp(value).ok().flatten(),
+                "SlurmdStartTime" => node.slurmd_start_time = parse_timestamp(value).ok().flatten(),
+                "LastBusyTime" => node.last_busy_time = parse_timestamp(value).ok().flatten(),
+                "ResumeAfterTime" => node.resume_after_time = parse_timestamp(value).ok().flatten(),
+                "CfgTRES" => node.cfg_tres = value.parse().ok(),
+                "AllocTRES" => node.alloc_tres = value.parse().ok(),
                 "CapWatts" => node.cap_watts = Some(value.to_string()),
                 "CurrentWatts" => node.current_watts = value.parse().ok(),
                 "AveWatts" => node.ave_watts = value.parse().ok(),
@@ -155,36 +225,29 @@ impl Node {
         node
     }
 
-    /// Parse the `scontrol` output into a vector of `Node` structs.
-    pub fn fetch_and_parse_nodes() -> Result<Vec<Self>, Box<dyn Error>> {
-        let output = std::process::Command::new("scontrol")
-            .args(["show", "node", "-a", "--oneliner"])
-            .output()?;
-
-        if !output.status.success() {
-            return Err(format!(
-                "Failed to execute scontrol: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )
-            .into());
-        }
-
-        let stdout = String::from_utf8(output.stdout)?;
+    /// Fetch the node records from `source` and parse them into a vector of `Node` structs.
+    pub fn fetch_and_parse_nodes(source: &dyn SlurmSource) -> Result<Vec<Self>, SlurmToolError> {
+        let stdout = source.show(Entity::Node)?;
+        Node::parse_nodes(&stdout)
+    }
 
+    /// Parse `scontrol show node` output into a vector of `Node` structs.
+    ///
+    /// Accepts both `--json` documents and `-a --oneliner` records.
+    pub fn parse_nodes(stdout: &str) -> Result<Vec<Self>, SlurmToolError> {
+        if json::is_json(stdout) {
+            return json::parse_nodes(stdout);
+        }
 
         let mut nodes = Vec::new();
 
-        for line in stdout.lines() {
-            let fields: Vec<(&str, &str)> = line
-                .split_whitespace()
-                .filter_map(|kv| {
-                    let mut parts = kv.splitn(2, '=');
-                    match (parts.next(), parts.next()) {
-                        (Some(key), Some(value)) => Some((key, value)),
-                        _ => None,
-                    }
-                })
-                .collect();
+        for (number, line) in stdout.lines().enumerate() {
+            let fields = oneliner::tokenize(line, NODE_KEYS);
+
+            if fields.is_empty() {
+                continue;
+            }
+            oneliner::check_record(&fields, "NodeName", number + 1)?;
 
             nodes.push(Node::from_fields(&fields));
         }
@@ -192,33 +255,139 @@ impl Node {
         Ok(nodes)
     }
 
-	pub fn free_memory(&self) -> Option<Memory> {
-		match (self.real_memory, self.allocated_memory) {
-			(Some(real), Some(allocated)) => Some(Memory::new(real.as_kb().saturating_sub(allocated.as_kb()))),
-			_ => None,
-		}
-	}
-
-    pub fn pretty_memory(&self, unit: &str) -> String {
-        let real = self.real_memory.map(|m| match unit {
-            "GB" => format!("{:.2} GB", m.as_gb()),
-            "MB" => format!("{:.0} MB", m.as_mb()),
-            _ => format!("{} KB", m.as_kb()),
-        }).unwrap_or_else(|| "N/A".to_string());
-    
-        let allocated = self.allocated_memory.map(|m| match unit {
-            "GB" => format!("{:.2} GB", m.as_gb()),
-            "MB" => format!("{:.0} MB", m.as_mb()),
-            _ => format!("{} KB", m.as_kb()),
-        }).unwrap_or_else(|| "N/A".to_string());
-    
-        let free = self.free_memory().map(|m| match unit {
-            "GB" => format!("{:.2} GB", m.as_gb()),
-            "MB" => format!("{:.0} MB", m.as_mb()),
-            _ => format!("{} KB", m.as_kb()),
-        }).unwrap_or_else(|| "N/A".to_string());
-    
-        format!("Real: {}, Allocated: {}, Free: {}", real, allocated, free)
+    /// Time since the node booted.
+    pub fn uptime(&self, now: &DateTime<Local>) -> Option<SlurmDuration> {
+        self.boot_time.map(|boot| timestamp::elapsed(&boot, now))
+    }
+
+    /// Time since slurmd on the node was (re)started.
+    pub fn slurmd_uptime(&self, now: &DateTime<Local>) -> Option<SlurmDuration> {
+        self.slurmd_start_time.map(|start| timestamp::elapsed(&start, now))
+    }
+
+    /// How long an idle node has been without jobs, from `LastBusyTime`.
+    ///
+    /// `None` for nodes that are running jobs or whose last busy time is unknown.
+    pub fn idle_for(&self, now: &DateTime<Local>) -> Option<SlurmDuration> {
+        let idle = self.state.as_ref().is_some_and(|s| s.base == BaseState::Idle)
+            && self.cpu_alloc.unwrap_or(0) == 0;
+        if !idle {
+            return None;
+        }
+        self.last_busy_time.map(|busy| timestamp::elapsed(&busy, now))
+    }
+
+    /// Configured GRES minus `GresUsed`, e.g. `gpu:a100:1` when three of four A100s are busy.
+    pub fn free_gres(&self) -> Vec<Gres> {
+        gres::free_gres(self)
+    }
+
+    /// Configured TRES minus allocated TRES, e.g. the GPUs nobody is using right now.
+    pub fn free_tres(&self) -> Option<Tres> {
+        let cfg = self.cfg_tres.as_ref()?;
+        Some(match &self.alloc_tres {
+            Some(alloc) => cfg.saturating_sub(alloc),
+            None => cfg.clone(),
+        })
+    }
+
+    /// Memory not allocated to jobs. Without an `AllocMem` value nothing is assumed allocated.
+    pub fn free_memory(&self) -> Option<Memory> {
+        let real = self.real_memory?;
+        Some(real - self.allocated_memory.unwrap_or_default())
+    }
+
+    pub fn pretty_memory(&self, units: DisplayUnits) -> String {
+        format!(
+            "Real: {}, Allocated: {}, Free: {}",
+            units.format_optional(self.real_memory),
+            units.format_optional(self.allocated_memory),
+            units.format_optional(self.free_memory())
+        )
+    }
+
+    /// CPUs not allocated to jobs.
+    pub fn cpu_free(&self) -> Option<u32> {
+        Some(self.cpu_total?.saturating_sub(self.cpu_alloc.unwrap_or(0)))
+    }
+
+    /// The value of one of `NODE_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str, now: &DateTime<Local>) -> Value {
+        let join = |items: Vec<String>| {
+            if items.is_empty() { Value::Empty } else { Value::Text(items.join(",")) }
+        };
+        let gres = |items: Vec<Gres>| join(items.iter().map(Gres::to_string).collect());
+        let count = |value: Option<u32>| Value::from_option(value, |v| Value::Count(v.into()));
+
+        match column {
+            "name" => Value::Text(self.name.clone()),
+            "state" => Value::from_option(self.state.as_ref(), |s| Value::Text(s.to_string())),
+            "partitions" => join(self.partitions.clone()),
+            "cpu_total" => count(self.cpu_total),
+            "cpu_alloc" => count(self.cpu_alloc),
+            "cpu_free" => count(self.cpu_free()),
+            "cpu_load" => Value::from_option(self.cpu_load, Value::Float),
+            "mem_total" => Value::from_option(self.real_memory, Value::Memory),
+            "mem_alloc" => Value::from_option(self.allocated_memory, Value::Memory),
+            "mem_free" => Value::from_option(self.free_memory(), Value::Memory),
+            "gres" => gres(self.gres.clone()),
+            "gres_used" => gres(self.gres_used.clone()),
+            "gres_free" => gres(self.free_gres()),
+            "features" => join(self.active_features.clone()),
+            "uptime" => Value::from_option(self.uptime(now), Value::Duration),
+            "idle_for" => Value::from_option(self.idle_for(now), Value::Duration),
+            "reason" => Value::from_option(self.reason.clone(), Value::Text),
+            "jobs" => join(self.jobs.iter().map(|job| job.job_id.clone()).collect()),
+            "users" => {
+                let mut users: Vec<String> = self.jobs.iter().filter_map(|job| job.user.clone()).collect();
+                users.sort();
+                users.dedup();
+                join(users)
+            }
+            "next_reservation" => {
+                let next = self.next_reservation(now).and_then(|r| Some((&r.name, r.starts_in(now)?)));
+                Value::from_option(next, |(name, starts_in)| Value::Text(format!("{} in {}", name, starts_in)))
+            }
+            _ => Value::Empty,
+        }
+    }
+
+    /// Like `value`, with the state column coloured by state.
+    pub fn cell(&self, column: &str, now: &DateTime<Local>) -> Cell {
+        match (column, &self.state) {
+            ("state", Some(state)) => Cell::colored(self.value(column, now), state.color()),
+            _ => Cell::new(self.value(column, now)),
+        }
+    }
+
+    /// The first reservation that starts after `now`, once joined with
+    /// `NodeMap::attach_reservations`.
+    pub fn next_reservation(&self, now: &DateTime<Local>) -> Option<&NodeReservation> {
+        self.reservations.iter().find(|reservation| reservation.starts_in(now).is_some())
+    }
+
+    /// Every field except `jobs` and `reservations` as `(name, value)` text in declaration order, for detail
+    /// views. Memory sizes are formatted in `units`.
+    pub fn fields(&self, units: DisplayUnits) -> Vec<(String, String)> {
+        let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(self) else {
+            return Vec::new();
+        };
+        let join_gres = |gres: &[Gres]| gres.iter().map(Gres::to_string).collect::<Vec<_>>().join(",");
+        fields
+            .into_iter()
+            .filter(|(field, _)| field != "jobs" && field != "reservations")
+            .map(|(field, value)| {
+                let value = match (field.as_str(), value) {
+                    ("gres", _) => join_gres(&self.gres),
+                    ("gres_used", _) => join_gres(&self.gres_used),
+                    (name, serde_json::Value::Number(mb)) if MEMORY_FIELDS.contains(&name) => {
+                        units.format(Memory::new(mb.as_u64().unwrap_or(0)))
+                    }
+                    (_, value) => render_json(value),
+                };
+                (field, value)
+            })
+            .collect()
     }
 
     pub fn pretty_cpu(&self) -> String {
@@ -232,11 +401,27 @@ impl Node {
     }
 }
 
+/// Plain text for a serialized field: `-` for unset, lists joined with commas, maps as
+/// `key=value` pairs.
+fn render_json(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "-".to_string(),
+        serde_json::Value::String(text) => text,
+        serde_json::Value::Array(items) => items.into_iter().map(render_json).collect::<Vec<_>>().join(","),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, render_json(value)))
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
 
 // tests for the node.rs file
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::source::StaticSource;
 
       #[test]
       fn test_node_from_fields() {
@@ -264,7 +449,7 @@ mod tests {
           assert_eq!(node.cpu_load, Some(0.00));
           assert_eq!(node.available_features, vec!["feature1".to_string(), "feature2".to_string()]);
           assert_eq!(node.active_features, vec!["feature1".to_string()]);
-          assert_eq!(node.gres, Some("gpu:1".to_string()));
+          assert_eq!(node.gres, vec![Gres::new("gpu", None, 1)]);
         }
 
       #[test]
@@ -285,7 +470,7 @@ mod tests {
         assert_eq!(node.cpu_load, None);
         assert!(node.available_features.is_empty());
         assert!(node.active_features.is_empty());
-        assert_eq!(node.gres, None);
+        assert!(node.gres.is_empty());
       }
 
       #[test]
@@ -303,7 +488,7 @@ mod tests {
         assert_eq!(node.cpu_load, None);
         assert!(node.available_features.is_empty());
         assert!(node.active_features.is_empty());
-        assert_eq!(node.gres, None);
+        assert!(node.gres.is_empty());
       }
 
       #[test]
@@ -323,4 +508,125 @@ mod tests {
         assert_eq!(node.cpu_load, None);
       }
 
-}
\ No newline at end of file
+      #[test]
+      fn test_node_map_from_source() {
+        let source = StaticSource::new(
+            "NodeName=node1 Arch=x86_64 CPUTot=64 RealMemory=512000\n\
+             NodeName=node2 Arch=x86_64 CPUTot=32 RealMemory=256000\n",
+        );
+
+        let node_map = NodeMap::build(&source).unwrap();
+
+        assert_eq!(node_map.nodes.len(), 2);
+        assert_eq!(node_map.get("node2").unwrap().cpu_total, Some(32));
+      }
+
+      #[test]
+      fn test_node_map_duplicate_name() {
+        let source = StaticSource::new("NodeName=node1\nNodeName=node1\n");
+
+        assert!(matches!(
+            NodeMap::build(&source),
+            Err(SlurmToolError::Duplicate { kind: "node", .. })
+        ));
+      }
+
+      #[test]
+      fn test_parse_nodes_reports_line() {
+        let error = Node::parse_nodes("NodeName=node1\nCPUTot=4 NodeName=node2\n").unwrap_err();
+        assert_eq!(error.to_string(), "Record does not start with NodeName= (line 2, field NodeName)");
+
+        let error = Node::parse_nodes("NodeName= CPUTot=4\n").unwrap_err();
+        assert_eq!(error.to_string(), "Empty NodeName (line 1, field NodeName)");
+      }
+
+      #[test]
+      fn test_parse_nodes_values_with_spaces() {
+        let stdout = "NodeName=node01 Arch=x86_64 CoresPerSocket=16 CPUAlloc=0 CPUEfctv=32 CPUTot=32 \
+            CPULoad=0.01 AvailableFeatures=avx2 ActiveFeatures=avx2 Gres=(null) NodeAddr=node01 \
+            NodeHostName=node01 Version=23.02.7 OS=Linux 4.18.0-477.el8.x86_64 #1 SMP Wed May 17 2023 \
+            RealMemory=256000 AllocMem=0 FreeMem=250000 Sockets=2 Boards=1 State=IDLE+DRAIN \
+            ThreadsPerCore=1 TmpDisk=0 Weight=1 Owner=N/A MCS_label=N/A Partitions=all \
+            BootTime=2024-01-01T00:00:00 SlurmdStartTime=2024-01-01T00:05:00 \
+            LastBusyTime=2024-01-29T12:00:00 ResumeAfterTime=None CfgTRES=cpu=32,mem=250G,billing=32 \
+            AllocTRES= CapWatts=n/a CurrentWatts=0 AveWatts=0 ExtSensorsJoules=n/a \
+            ExtSensorsWatts=0 ExtSensorsTemp=n/a Reason=Not responding [slurm@2024-01-01T00:00:00]\n";
+
+        let nodes = Node::parse_nodes(stdout).unwrap();
+
+        assert_eq!(nodes.len(), 1);
+        let node = &nodes[0];
+        assert_eq!(node.os.as_deref(), Some("Linux 4.18.0-477.el8.x86_64 #1 SMP Wed May 17 2023"));
+        assert_eq!(node.reason.as_deref(), Some("Not responding [slurm@2024-01-01T00:00:00]"));
+        assert_eq!(node.cfg_tres.as_ref().unwrap().to_string(), "billing=32,cpu=32,mem=250G");
+        assert!(node.alloc_tres.as_ref().unwrap().is_empty());
+        assert_eq!(node.free_tres().unwrap().cpu(), Some(32));
+        assert_eq!(node.free_memory(), Some(Memory::new(256000)));
+
+        let now = parse_timestamp("2024-01-31T12:00:00").unwrap().unwrap();
+        assert_eq!(node.uptime(&now).unwrap().to_string(), "30d12h");
+        assert_eq!(node.idle_for(&now).unwrap().to_string(), "2d");
+        assert_eq!(node.value("idle_for", &now), Value::Duration(SlurmDuration::from_secs(2 * 86400)));
+        assert_eq!(node.value("cpu_free", &now), Value::Count(32));
+        assert_eq!(node.value("gres", &now), Value::Empty);
+        assert_eq!(node.resume_after_time, None);
+        assert!(node.state.as_ref().unwrap().is_drained());
+      }
+
+      #[test]
+      fn test_free_memory_large_node() {
+        let node = Node::from_fields(&[("RealMemory", "6291456"), ("AllocMem", "1048576")]);
+
+        assert_eq!(node.free_memory(), Some("5T".parse().unwrap()));
+        assert_eq!(
+            node.pretty_memory("tib".parse().unwrap()),
+            "Real: 6 TiB, Allocated: 1 TiB, Free: 5 TiB"
+        );
+      }
+
+      #[test]
+      fn test_attach_jobs() {
+          let source = StaticSource::new(
+              "NodeName=gpu01 CPUTot=64 RealMemory=512000 State=MIXED\n\
+               NodeName=gpu02 CPUTot=64 RealMemory=512000 State=MIXED\n\
+               JobId=1 UserId=alice(1000) JobState=RUNNING NodeList=gpu[01-02] NumNodes=2 AllocTRES=cpu=64,mem=200G,node=2\n\
+               JobId=2 UserId=bob(1001) JobState=RUNNING NodeList=gpu01 NumNodes=1 AllocTRES=cpu=8,mem=16G,node=1\n\
+               JobId=3 UserId=carol(1002) JobState=COMPLETED NodeList=gpu02 NumNodes=1 AllocTRES=cpu=8,node=1\n",
+          );
+          let mut nodes = NodeMap::build(&source).unwrap();
+          nodes.attach_jobs(&JobMap::build(&source).unwrap());
+
+          let gpu01 = nodes.get("gpu01").unwrap();
+          assert_eq!(gpu01.value("jobs", &Local::now()), Value::Text("1,2".to_string()));
+          assert_eq!(gpu01.value("users", &Local::now()), Value::Text("alice,bob".to_string()));
+          assert_eq!(gpu01.jobs[0].tres.cpu(), Some(32));
+          assert_eq!(gpu01.jobs[0].tres.mem(), Some("100G".parse().unwrap()));
+
+          let gpu02 = nodes.get("gpu02").unwrap();
+          assert_eq!(gpu02.jobs.len(), 1);
+          assert!(gpu02.fields(DisplayUnits::default()).contains(&("real_memory".to_string(), "500 GiB".to_string())));
+      }
+
+      #[test]
+      fn test_attach_reservations() {
+          let source = StaticSource::new(
+              "NodeName=node01 CPUTot=32 State=IDLE\n\
+               NodeName=node02 CPUTot=32 State=IDLE\n\
+               ReservationName=maint StartTime=2024-02-01T08:00:00 EndTime=2024-02-01T20:00:00 Nodes=node[01-02] Flags=MAINT State=INACTIVE\n\
+               ReservationName=old StartTime=2024-01-01T08:00:00 EndTime=2024-01-01T20:00:00 Nodes=node01 State=INACTIVE\n\
+               ReservationName=course StartTime=2024-01-20T00:00:00 EndTime=2024-03-01T00:00:00 Nodes=node02 State=ACTIVE\n",
+          );
+          let now = parse_timestamp("2024-01-31T06:00:00").unwrap().unwrap();
+          let mut nodes = NodeMap::build(&source).unwrap();
+          nodes.attach_reservations(&ReservationMap::build(&source).unwrap(), &now);
+
+          let node01 = nodes.get("node01").unwrap();
+          assert_eq!(node01.reservations.len(), 1);
+          assert_eq!(node01.value("next_reservation", &now), Value::Text("maint in 1d2h".to_string()));
+
+          let node02 = nodes.get("node02").unwrap();
+          assert_eq!(node02.reservations[0].name, "course");
+          assert_eq!(node02.next_reservation(&now).map(|r| r.name.as_str()), Some("maint"));
+      }
+
+}
@@ -0,0 +1,339 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use termcolor::Color;
+
+/// The base state of a node, the part of `State=` before the first `+`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BaseState {
+    #[default]
+    Unknown,
+    Down,
+    Idle,
+    Allocated,
+    Error,
+    Mixed,
+    Future,
+}
+
+impl BaseState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BaseState::Unknown => "UNKNOWN",
+            BaseState::Down => "DOWN",
+            BaseState::Idle => "IDLE",
+            BaseState::Allocated => "ALLOCATED",
+            BaseState::Error => "ERROR",
+            BaseState::Mixed => "MIXED",
+            BaseState::Future => "FUTURE",
+        }
+    }
+}
+
+/// A state flag, one of the `+FLAG` parts of `State=` or a sinfo style suffix such as `*`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StateFlag {
+    NotResponding,
+    Drain,
+    Completing,
+    Reserved,
+    Maint,
+    Fail,
+    Cloud,
+    PoweredDown,
+    PoweringDown,
+    PoweringUp,
+    PowerDown,
+    RebootRequested,
+    RebootIssued,
+    Planned,
+    InvalidReg,
+    Dynamic,
+    Blocked,
+    /// A flag this version of slurmtool does not know about.
+    Other(String),
+}
+
+impl StateFlag {
+    pub fn as_str(&self) -> &str {
+        match self {
+            StateFlag::NotResponding => "NOT_RESPONDING",
+            StateFlag::Drain => "DRAIN",
+            StateFlag::Completing => "COMPLETING",
+            StateFlag::Reserved => "RESERVED",
+            StateFlag::Maint => "MAINTENANCE",
+            StateFlag::Fail => "FAIL",
+            StateFlag::Cloud => "CLOUD",
+            StateFlag::PoweredDown => "POWERED_DOWN",
+            StateFlag::PoweringDown => "POWERING_DOWN",
+            StateFlag::PoweringUp => "POWERING_UP",
+            StateFlag::PowerDown => "POWER_DOWN",
+            StateFlag::RebootRequested => "REBOOT_REQUESTED",
+            StateFlag::RebootIssued => "REBOOT_ISSUED",
+            StateFlag::Planned => "PLANNED",
+            StateFlag::InvalidReg => "INVALID_REG",
+            StateFlag::Dynamic => "DYNAMIC_NORM",
+            StateFlag::Blocked => "BLOCKED",
+            StateFlag::Other(flag) => flag,
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "NOT_RESPONDING" | "NO_RESPOND" => StateFlag::NotResponding,
+            "DRAIN" | "DRAINED" | "DRAINING" => StateFlag::Drain,
+            "COMPLETING" => StateFlag::Completing,
+            "RESERVED" | "RESV" => StateFlag::Reserved,
+            "MAINT" | "MAINTENANCE" => StateFlag::Maint,
+            "FAIL" | "FAILING" => StateFlag::Fail,
+            "CLOUD" => StateFlag::Cloud,
+            "POWERED_DOWN" | "POWER_SAVE" => StateFlag::PoweredDown,
+            "POWERING_DOWN" => StateFlag::PoweringDown,
+            "POWERING_UP" | "POWER_UP" => StateFlag::PoweringUp,
+            "POWER_DOWN" => StateFlag::PowerDown,
+            "REBOOT_REQUESTED" | "REBOOT" => StateFlag::RebootRequested,
+            "REBOOT_ISSUED" => StateFlag::RebootIssued,
+            "PLANNED" => StateFlag::Planned,
+            "INVALID_REG" => StateFlag::InvalidReg,
+            "DYNAMIC_NORM" | "DYNAMIC_FUTURE" | "DYNAMIC" => StateFlag::Dynamic,
+            "BLOCKED" => StateFlag::Blocked,
+            other => StateFlag::Other(other.to_string()),
+        }
+    }
+
+    /// The flag for a sinfo style state suffix, e.g. `*` in `IDLE*`.
+    fn from_suffix(suffix: char) -> Option<Self> {
+        match suffix {
+            '*' => Some(StateFlag::NotResponding),
+            '~' => Some(StateFlag::PoweredDown),
+            '#' => Some(StateFlag::PoweringUp),
+            '%' => Some(StateFlag::PoweringDown),
+            '!' => Some(StateFlag::PowerDown),
+            '$' => Some(StateFlag::Maint),
+            '@' => Some(StateFlag::RebootRequested),
+            '^' => Some(StateFlag::RebootIssued),
+            '-' => Some(StateFlag::Planned),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed node `State=` value such as `MIXED+DRAIN+RESERVED` or `IDLE*`.
+///
+/// Serializes as the scontrol string.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeState {
+    pub base: BaseState,
+    pub flags: BTreeSet<StateFlag>,
+}
+
+impl NodeState {
+    pub fn new(base: BaseState) -> Self {
+        Self { base, flags: BTreeSet::new() }
+    }
+
+    pub fn has(&self, flag: &StateFlag) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Whether the node can start new jobs: idle or partly allocated, responding, and not
+    /// drained, failed, in maintenance or on its way down. Powered down cloud nodes count as
+    /// available because Slurm resumes them on demand.
+    pub fn is_available(&self) -> bool {
+        matches!(self.base, BaseState::Idle | BaseState::Mixed)
+            && ![
+                StateFlag::Drain,
+                StateFlag::NotResponding,
+                StateFlag::Fail,
+                StateFlag::Maint,
+                StateFlag::PoweringDown,
+                StateFlag::PowerDown,
+                StateFlag::RebootIssued,
+                StateFlag::InvalidReg,
+            ]
+            .iter()
+            .any(|flag| self.has(flag))
+    }
+
+    /// Drain was requested, whether or not jobs are still running.
+    pub fn is_drain(&self) -> bool {
+        self.has(&StateFlag::Drain)
+    }
+
+    /// Drain was requested but jobs are still running (sinfo `draining`).
+    pub fn is_draining(&self) -> bool {
+        self.is_drain()
+            && (matches!(self.base, BaseState::Allocated | BaseState::Mixed)
+                || self.has(&StateFlag::Completing))
+    }
+
+    /// Drain was requested and no jobs are left (sinfo `drained`).
+    pub fn is_drained(&self) -> bool {
+        self.is_drain() && !self.is_draining()
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.base == BaseState::Down
+    }
+
+    pub fn is_responding(&self) -> bool {
+        !self.has(&StateFlag::NotResponding)
+    }
+
+    /// Whether `name` matches the base state or one of the flags, ignoring case.
+    ///
+    /// Accepts the scontrol names (`idle`, `drain`, `not_responding`) as well as the derived
+    /// sinfo names `alloc`, `mix`, `draining`, `drained` and `available`.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_uppercase();
+        match name.as_str() {
+            "ALLOC" => self.base == BaseState::Allocated,
+            "MIX" => self.base == BaseState::Mixed,
+            "DRAINING" => self.is_draining(),
+            "DRAINED" => self.is_drained(),
+            "AVAILABLE" => self.is_available(),
+            _ => self.base.as_str() == name || self.has(&StateFlag::from_name(&name)),
+        }
+    }
+
+    /// Suggested colour when printing the state to a terminal.
+    pub fn color(&self) -> Color {
+        if self.is_down() || self.has(&StateFlag::Fail) || !self.is_responding() {
+            Color::Red
+        } else if self.is_drain() || self.has(&StateFlag::Maint) {
+            Color::Magenta
+        } else if self.has(&StateFlag::Reserved) {
+            Color::Cyan
+        } else {
+            match self.base {
+                BaseState::Idle => Color::Green,
+                BaseState::Mixed => Color::Yellow,
+                BaseState::Allocated => Color::Blue,
+                _ => Color::White,
+            }
+        }
+    }
+}
+
+impl FromStr for NodeState {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let mut parts = value.split('+');
+        let base = parts.next().filter(|b| !b.is_empty()).ok_or("Empty node state")?;
+
+        let mut state = NodeState::default();
+
+        // Peel off sinfo style suffixes such as `IDLE*` or `DOWN~`.
+        let base_name = base.trim_end_matches(|c| StateFlag::from_suffix(c).is_some());
+        for suffix in base[base_name.len()..].chars() {
+            state.flags.extend(StateFlag::from_suffix(suffix));
+        }
+
+        state.base = match base_name.to_uppercase().as_str() {
+            "DOWN" => BaseState::Down,
+            "IDLE" => BaseState::Idle,
+            "ALLOCATED" | "ALLOC" => BaseState::Allocated,
+            "ERROR" => BaseState::Error,
+            "MIXED" | "MIX" => BaseState::Mixed,
+            "FUTURE" | "FUTR" => BaseState::Future,
+            "UNKNOWN" | "UNK" => BaseState::Unknown,
+            // sinfo reports some flags in place of the base state
+            "DRAINED" | "DRAIN" => {
+                state.flags.insert(StateFlag::Drain);
+                BaseState::Idle
+            }
+            "DRAINING" | "DRNG" => {
+                state.flags.insert(StateFlag::Drain);
+                BaseState::Allocated
+            }
+            "COMPLETING" | "COMP" => {
+                state.flags.insert(StateFlag::Completing);
+                BaseState::Allocated
+            }
+            other => return Err(format!("Unknown node state '{}'", other)),
+        };
+
+        for flag in parts.filter(|f| !f.is_empty()) {
+            state.flags.insert(StateFlag::from_name(&flag.to_uppercase()));
+        }
+
+        Ok(state)
+    }
+}
+
+impl fmt::Display for NodeState {
+    /// Formats like scontrol: `IDLE*+DRAIN`, with `*` marking a node that is not responding.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base.as_str())?;
+        if !self.is_responding() {
+            write!(f, "*")?;
+        }
+        for flag in self.flags.iter().filter(|flag| **flag != StateFlag::NotResponding) {
+            write!(f, "+{}", flag.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for NodeState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node_state() {
+        let state: NodeState = "MIXED+DRAIN+RESERVED".parse().unwrap();
+        assert_eq!(state.base, BaseState::Mixed);
+        assert!(state.has(&StateFlag::Drain));
+        assert!(state.has(&StateFlag::Reserved));
+        assert!(state.is_draining());
+        assert!(!state.is_available());
+
+        let state: NodeState = "IDLE*".parse().unwrap();
+        assert_eq!(state.base, BaseState::Idle);
+        assert!(!state.is_responding());
+        assert!(!state.is_available());
+
+        let state: NodeState = "IDLE+CLOUD+POWERED_DOWN".parse().unwrap();
+        assert!(state.has(&StateFlag::Cloud));
+        assert!(state.is_available());
+
+        let state: NodeState = "DOWN*+DRAIN".parse().unwrap();
+        assert!(state.is_down());
+        assert!(state.is_drained());
+
+        let state: NodeState = "IDLE+NEW_FLAG".parse().unwrap();
+        assert!(state.has(&StateFlag::Other("NEW_FLAG".to_string())));
+
+        assert!("".parse::<NodeState>().is_err());
+        assert!("SLEEPING".parse::<NodeState>().is_err());
+    }
+
+    #[test]
+    fn test_node_state_matches() {
+        let state: NodeState = "MIXED+DRAIN".parse().unwrap();
+        assert!(state.matches("mixed"));
+        assert!(state.matches("mix"));
+        assert!(state.matches("drain"));
+        assert!(state.matches("draining"));
+        assert!(!state.matches("drained"));
+        assert!(!state.matches("idle"));
+        assert!(!state.matches("available"));
+    }
+
+    #[test]
+    fn test_display_node_state() {
+        for state in ["MIXED+DRAIN+RESERVED", "IDLE*", "DOWN*+DRAIN", "ALLOCATED"] {
+            assert_eq!(state.parse::<NodeState>().unwrap().to_string(), state);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::error::SlurmToolError;
+
+/// Splits one `scontrol show <entity> --oneliner` record into `(key, value)` pairs.
+///
+/// Values may contain spaces (`Reason=Not responding [slurm@...]`, `OS=Linux 4.18.0 #1 SMP`),
+/// so splitting on whitespace is not enough. A new field only starts at a word of the form
+/// `Key=...` where `Key` is one of `known_keys`; everything up to the next such word belongs
+/// to the previous value. Text before the first known key is ignored.
+pub fn tokenize<'a>(line: &'a str, known_keys: &[&str]) -> Vec<(&'a str, &'a str)> {
+    // (key, start of key, start of value)
+    let mut starts: Vec<(&str, usize, usize)> = Vec::new();
+
+    for (offset, word) in words(line) {
+        if let Some((key, _)) = word.split_once('=') {
+            if known_keys.contains(&key) {
+                starts.push((&line[offset..offset + key.len()], offset, offset + key.len() + 1));
+            }
+        }
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &(key, _, value_start))| {
+            let value_end = starts.get(i + 1).map_or(line.len(), |&(_, next, _)| next);
+            (key, line[value_start..value_end].trim())
+        })
+        .collect()
+}
+
+/// Checks that a tokenized record starts with a non-empty `record_key` (`NodeName`,
+/// `PartitionName`, ...); `line` is the 1-based line number used in the error.
+pub fn check_record(
+    fields: &[(&str, &str)],
+    record_key: &str,
+    line: usize,
+) -> Result<(), SlurmToolError> {
+    match fields.first() {
+        Some((key, value)) if *key == record_key && !value.is_empty() => Ok(()),
+        Some((key, _)) if *key == record_key => Err(SlurmToolError::Parse {
+            line: Some(line),
+            field: Some(record_key.to_string()),
+            message: format!("Empty {}", record_key),
+        }),
+        _ => Err(SlurmToolError::Parse {
+            line: Some(line),
+            field: Some(record_key.to_string()),
+            message: format!("Record does not start with {}=", record_key),
+        }),
+    }
+}
+
+/// The value as an owned string, or `None` for an empty value or Slurm's `(null)`.
+pub fn not_null(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty() && value != "(null)")
+}
+
+/// Whitespace separated words of `line` with their byte offsets.
+fn words(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - line.as_ptr() as usize, word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[&str] = &["NodeName", "OS", "RealMemory", "State", "Reason"];
+
+    #[test]
+    fn test_tokenize_values_with_spaces() {
+        let line = "NodeName=node01 OS=Linux 4.18.0-477.el8.x86_64 #1 SMP Wed May 17 2023 \
+                    RealMemory=256000 State=IDLE+DRAIN Reason=Not responding [slurm@2024-01-01T00:00:00]";
+
+        let fields = tokenize(line, KEYS);
+
+        assert_eq!(
+            fields,
+            vec![
+                ("NodeName", "node01"),
+                ("OS", "Linux 4.18.0-477.el8.x86_64 #1 SMP Wed May 17 2023"),
+                ("RealMemory", "256000"),
+                ("State", "IDLE+DRAIN"),
+                ("Reason", "Not responding [slurm@2024-01-01T00:00:00]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unknown_keys_stay_in_value() {
+        let line = "NodeName=node01 Reason=maintenance ticket=1234 by admin State=MAINT";
+
+        let fields = tokenize(line, KEYS);
+
+        assert_eq!(
+            fields,
+            vec![
+                ("NodeName", "node01"),
+                ("Reason", "maintenance ticket=1234 by admin"),
+                ("State", "MAINT"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_empty_values() {
+        let fields = tokenize("  NodeName=node01 Reason= State=IDLE  ", KEYS);
+
+        assert_eq!(fields, vec![("NodeName", "node01"), ("Reason", ""), ("State", "IDLE")]);
+    }
+}
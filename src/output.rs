@@ -0,0 +1,244 @@
+//! Machine-readable renderers for command output.
+//!
+//! Every subcommand collects its results as a list of `Serialize` records and hands them to
+//! [`write_records`]. JSON and YAML keep the records' structure; CSV and TSV flatten it into
+//! one row per record, with nested maps as dotted columns (`cfg_tres.cpu`) and lists joined
+//! with commas.
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::SlurmToolError;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The human-readable layout of each subcommand.
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 5] = ["text", "json", "yaml", "csv", "tsv"];
+
+    pub fn is_text(&self) -> bool {
+        *self == OutputFormat::Text
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!(
+                "Invalid format '{}', expected one of: {}",
+                value,
+                OutputFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Writes `records` to `writer` in `format`.
+///
+/// `Text` has no generic rendering; callers print their own layout and only call this for the
+/// other formats. Passing `Text` writes JSON.
+pub fn write_records<T: Serialize, W: Write>(
+    writer: &mut W,
+    format: OutputFormat,
+    records: &[T],
+) -> Result<(), SlurmToolError> {
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, records).map_err(output_error)?;
+            writeln!(writer).map_err(output_error)
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(writer, records).map_err(output_error),
+        OutputFormat::Csv => write_table(writer, records, ','),
+        OutputFormat::Tsv => write_table(writer, records, '\t'),
+    }
+}
+
+fn output_error(error: impl fmt::Display) -> SlurmToolError {
+    SlurmToolError::Output(error.to_string())
+}
+
+/// Writes a header row and one row per record. Columns are the union of all records' keys
+/// in first-seen order, so every row has the same width.
+fn write_table<T: Serialize, W: Write>(
+    writer: &mut W,
+    records: &[T],
+    delimiter: char,
+) -> Result<(), SlurmToolError> {
+    let mut rows = Vec::with_capacity(records.len());
+    let mut columns: Vec<String> = Vec::new();
+    for record in records {
+        let mut row = Vec::new();
+        flatten("", serde_json::to_value(record).map_err(output_error)?, &mut row);
+        for (column, _) in &row {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        rows.push(row);
+    }
+    // A map that is null in some records would otherwise also get a column of its own.
+    let nested: Vec<String> = columns
+        .iter()
+        .filter_map(|column| column.split_once('.').map(|(parent, _)| parent.to_string()))
+        .collect();
+    columns.retain(|column| !nested.contains(column));
+
+    let separator = delimiter.to_string();
+    let header: Vec<String> = columns.iter().map(|c| escape(c, delimiter)).collect();
+    writeln!(writer, "{}", header.join(&separator)).map_err(output_error)?;
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                row.iter()
+                    .find(|(key, _)| key == column)
+                    .map_or_else(String::new, |(_, value)| escape(value, delimiter))
+            })
+            .collect();
+        writeln!(writer, "{}", cells.join(&separator)).map_err(output_error)?;
+    }
+    Ok(())
+}
+
+/// Flattens `value` into `(column, cell)` pairs.
+fn flatten(prefix: &str, value: Value, row: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            // An empty map adds no columns; its cells stay blank.
+            for (key, value) in map {
+                let column = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten(&column, value, row);
+            }
+        }
+        value => row.push((prefix.to_string(), cell(value))),
+    }
+}
+
+/// Renders a leaf value; lists of scalars are joined with commas, anything else nested stays
+/// JSON.
+fn cell(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text,
+        Value::Array(items) if items.iter().all(|item| !item.is_object() && !item.is_array()) => {
+            items.into_iter().map(cell).collect::<Vec<_>>().join(",")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// CSV quotes cells that contain the delimiter, quotes or line breaks (RFC 4180); TSV has no
+/// quoting, so those characters are replaced by spaces.
+fn escape(value: &str, delimiter: char) -> String {
+    let special = |c: char| c == delimiter || c == '"' || c == '\n' || c == '\r';
+    if delimiter == '\t' {
+        value.replace(['\t', '\n', '\r'], " ")
+    } else if value.contains(special) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: String,
+        cpus: u32,
+        features: Vec<String>,
+        tres: BTreeMap<String, u64>,
+        reason: Option<String>,
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                name: "node01".to_string(),
+                cpus: 32,
+                features: vec!["avx2".to_string(), "ib".to_string()],
+                tres: BTreeMap::from([("cpu".to_string(), 32)]),
+                reason: Some("Not responding, \"maybe\"".to_string()),
+            },
+            Record {
+                name: "gpu01".to_string(),
+                cpus: 64,
+                features: Vec::new(),
+                tres: BTreeMap::from([("cpu".to_string(), 64), ("gres/gpu".to_string(), 4)]),
+                reason: None,
+            },
+            Record {
+                name: "login".to_string(),
+                cpus: 8,
+                features: Vec::new(),
+                tres: BTreeMap::new(),
+                reason: None,
+            },
+        ]
+    }
+
+    fn render(format: OutputFormat) -> String {
+        let mut buffer = Vec::new();
+        write_records(&mut buffer, format, &records()).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_csv_and_tsv() {
+        assert_eq!(
+            render(OutputFormat::Csv),
+            "name,cpus,features,tres.cpu,reason,tres.gres/gpu\n\
+             node01,32,\"avx2,ib\",32,\"Not responding, \"\"maybe\"\"\",\n\
+             gpu01,64,,64,,4\n\
+             login,8,,,,\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Tsv).lines().nth(1).unwrap(),
+            "node01\t32\tavx2,ib\t32\tNot responding, \"maybe\"\t"
+        );
+    }
+
+    #[test]
+    fn test_json_and_yaml() {
+        let json: Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json[1]["tres"]["gres/gpu"], 4);
+        assert_eq!(json[1]["reason"], Value::Null);
+
+        assert!(render(OutputFormat::Yaml).starts_with("- name: node01\n  cpus: 32\n"));
+    }
+}
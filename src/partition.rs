@@ -1,6 +1,33 @@
-use std::error::Error;
 use std::collections::BTreeMap;
 
+use serde::Serialize;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::hostlist;
+use crate::gres;
+use crate::memory::Memory;
+use crate::json;
+use crate::node::{Node, NodeMap};
+use crate::node_state::BaseState;
+use crate::oneliner;
+use crate::source::{Entity, SlurmSource};
+use crate::tres::{BillingWeights, Tres};
+
+/// Every key `scontrol show partition --oneliner` may print, including the ones `Partition`
+/// ignores. See `node::NODE_KEYS` for why the list has to be complete.
+pub const PARTITION_KEYS: &[&str] = &[
+    "PartitionName", "AllowGroups", "AllowAccounts", "DenyAccounts", "AllowQos", "DenyQos",
+    "AllocNodes", "Default", "QoS", "DefaultTime", "DisableRootJobs", "ExclusiveUser",
+    "ExclusiveTopo", "GraceTime", "Hidden", "MaxNodes", "MaxTime", "MinNodes", "LLN",
+    "MaxCPUsPerNode", "MaxCPUsPerSocket", "NodeSets", "Nodes", "PriorityJobFactor",
+    "PriorityTier", "RootOnly", "ReqResv", "OverSubscribe", "OverTimeLimit", "PreemptMode",
+    "State", "TotalCPUs", "TotalNodes", "SelectTypeParameters", "JobDefaults", "DefMemPerCPU",
+    "DefMemPerNode", "MaxMemPerCPU", "MaxMemPerNode", "TRES", "TRESBillingWeights",
+    "Alternate", "CpuBind", "PowerDownOnIdle", "ResumeTimeout", "SuspendTimeout",
+    "SuspendTime",
+];
+
 #[derive(Debug, Default)]
 pub struct PartitionMap {
     pub partitions: BTreeMap<String, Partition>,
@@ -8,14 +35,14 @@ pub struct PartitionMap {
 
 
 impl PartitionMap {
-    pub fn build() -> Result<Self, Box<dyn Error>> {
-        let partitions = Partition::fetch_and_parse_partitions()?;
+    pub fn build(source: &dyn SlurmSource) -> Result<Self, SlurmToolError> {
+        let partitions = Partition::fetch_and_parse_partitions(source)?;
         let mut partition_map = PartitionMap::default();
 
         for partition in partitions {
             // make sure that the partition name is unique
             if partition_map.partitions.contains_key(&partition.name) {
-                return Err(format!("Duplicate partition name: {}", partition.name).into());
+                return Err(SlurmToolError::Duplicate { kind: "partition", name: partition.name });
             }
 
             partition_map.partitions.insert(partition.name.clone(), partition);
@@ -27,9 +54,14 @@ impl PartitionMap {
     pub fn get(&self, name: &str) -> Option<&Partition> {
         self.partitions.get(name)
     }
+
+    /// Like `get`, but a missing partition is an error.
+    pub fn lookup(&self, name: &str) -> Result<&Partition, SlurmToolError> {
+        self.get(name).ok_or_else(|| SlurmToolError::UnknownPartition(name.to_string()))
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct Partition {
     pub name: String,
     pub allow_groups: Option<String>,
@@ -38,13 +70,13 @@ pub struct Partition {
     pub alloc_nodes: Option<String>,
     pub default: bool,
     pub qos: Option<String>,
-    pub default_time: Option<String>,
+    pub default_time: Option<SlurmDuration>,
     pub disable_root_jobs: bool,
     pub exclusive_user: bool,
     pub grace_time: Option<u32>,
     pub hidden: bool,
     pub max_nodes: Option<u32>,
-    pub max_time: Option<String>,
+    pub max_time: Option<SlurmDuration>,
     pub min_nodes: Option<u32>,
     pub lln: bool,
     pub max_cpus_per_node: Option<String>,
@@ -55,17 +87,17 @@ pub struct Partition {
     pub root_only: bool,
     pub req_resv: bool,
     pub oversubscribe: bool,
-    pub over_time_limit: Option<String>,
+    pub over_time_limit: Option<SlurmDuration>,
     pub preempt_mode: Option<String>,
     pub state: Option<String>,
     pub total_cpus: Option<u32>,
     pub total_nodes: Option<u32>,
     pub select_type_parameters: Option<String>,
     pub job_defaults: Option<String>,
-    pub def_mem_per_cpu: Option<u32>,
-    pub max_mem_per_node: Option<u32>,
-    pub tres: Option<String>,
-    pub tres_billing_weights: Option<String>,
+    pub def_mem_per_cpu: Option<Memory>,
+    pub max_mem_per_node: Option<Memory>,
+    pub tres: Option<Tres>,
+    pub tres_billing_weights: Option<BillingWeights>,
 }
 impl Partition {
     pub fn from_fields(fields: &[(&str, &str)]) -> Self {
@@ -81,7 +113,6 @@ impl Partition {
                 }
                 "AllowAccounts" => {
                     partition.allow_accounts = value
-                        .to_string()
                         .split(',')
                         .map(|s| s.to_string())
                         .collect();
@@ -96,10 +127,10 @@ impl Partition {
                     partition.default = *value == "YES";
                 }
                 "QoS" => {
-                    partition.qos = Some(value.to_string());
+                    partition.qos = Some(value.to_string()).filter(|qos| qos != "N/A");
                 }
                 "DefaultTime" => {
-                    partition.default_time = Some(value.to_string());
+                    partition.default_time = SlurmDuration::parse_optional(value).ok().flatten();
                 }
                 "DisableRootJobs" => {
                     partition.disable_root_jobs = *value == "YES";
@@ -117,7 +148,7 @@ impl Partition {
                     partition.max_nodes = value.parse().ok();
                 }
                 "MaxTime" => {
-                    partition.max_time = Some(value.to_string());
+                    partition.max_time = SlurmDuration::parse_optional(value).ok().flatten();
                 }
                 "MinNodes" => {
                     partition.min_nodes = value.parse().ok();
@@ -132,7 +163,10 @@ impl Partition {
                     partition.max_cpus_per_socket = Some(value.to_string());
                 }
                 "Nodes" => {
-                    partition.nodes = expand_node_range(value);
+                    partition.nodes = expand_node_range(value).unwrap_or_else(|e| {
+                        log::warn!("Ignoring node list of partition '{}': {}", partition.name, e);
+                        Vec::new()
+                    });
                 }
                 "PriorityJobFactor" => {
                     partition.priority_job_factor = value.parse().ok();
@@ -150,7 +184,7 @@ impl Partition {
                     partition.oversubscribe = *value == "YES";
                 }
                 "OverTimeLimit" => {
-                    partition.over_time_limit = Some(value.to_string());
+                    partition.over_time_limit = SlurmDuration::parse_optional(value).ok().flatten();
                 }
                 "PreemptMode" => {
                     partition.preempt_mode = Some(value.to_string());
@@ -177,10 +211,10 @@ impl Partition {
                     partition.max_mem_per_node = value.parse().ok();
                 }
                 "TRES" => {
-                    partition.tres = Some(value.to_string());
+                    partition.tres = value.parse().ok();
                 }
                 "TRESBillingWeights" => {
-                    partition.tres_billing_weights = Some(value.to_string());
+                    partition.tres_billing_weights = value.parse().ok();
                 }
                 _ => {} // Ignore any unknown keys
             }
@@ -189,33 +223,40 @@ impl Partition {
         partition
     }
 
-    pub fn fetch_and_parse_partitions() -> Result<Vec<Self>, Box<dyn Error>> {
+    /// Whether a job asking for `time_limit` is within the partition's `MaxTime`.
+    /// A partition without a `MaxTime` accepts any limit.
+    pub fn allows_time(&self, time_limit: SlurmDuration) -> bool {
+        self.max_time.is_none_or(|max_time| time_limit <= max_time)
+    }
 
-        let output = std::process::Command::new("scontrol")
-            .args(["show", "partition", "-a", "--oneliner"])
-            .output()?;
-        let mut partitions = Vec::new();
+    /// The time limit a job gets when it does not ask for one: `DefaultTime`, else `MaxTime`.
+    pub fn effective_default_time(&self) -> Option<SlurmDuration> {
+        self.default_time.or(self.max_time)
+    }
+
+    /// Fetch the partition records from `source` and parse them into a vector of `Partition` structs.
+    pub fn fetch_and_parse_partitions(source: &dyn SlurmSource) -> Result<Vec<Self>, SlurmToolError> {
+        let stdout = source.show(Entity::Partition)?;
+        Partition::parse_partitions(&stdout)
+    }
 
-        if !output.status.success() {
-            return Err(format!(
-                "Failed to execute scontrol: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )
-            .into());
+    /// Parse `scontrol show partition` output into a vector of `Partition` structs.
+    ///
+    /// Accepts both `--json` documents and `-a --oneliner` records.
+    pub fn parse_partitions(stdout: &str) -> Result<Vec<Self>, SlurmToolError> {
+        if json::is_json(stdout) {
+            return json::parse_partitions(stdout);
         }
-        let stdout = String::from_utf8(output.stdout)?;
-
-        for line in stdout.lines() {
-            let fields: Vec<(&str, &str)> = line
-                .split_whitespace()
-                .filter_map(|kv| {
-                    let mut parts = kv.splitn(2, '=');
-                    match (parts.next(), parts.next()) {
-                        (Some(key), Some(value)) => Some((key, value)),
-                        _ => None,
-                    }
-                })
-                .collect();
+
+        let mut partitions = Vec::new();
+
+        for (number, line) in stdout.lines().enumerate() {
+            let fields = oneliner::tokenize(line, PARTITION_KEYS);
+
+            if fields.is_empty() {
+                continue;
+            }
+            oneliner::check_record(&fields, "PartitionName", number + 1)?;
 
             partitions.push(Partition::from_fields(&fields));
         }
@@ -224,74 +265,109 @@ impl Partition {
     }
 }
 
-// example value:
-// "node[39-40,42-43]"  -> ['node39', 'node40', 'node42', 'node43']
-// OR 
-// "myslumbox,node[39-40,42-43]" -> ['myslumbox', 'node39', 'node40', 'node42', 'node43']
-fn expand_node_range(value_from_output: &str) -> Vec<String> {
-    let mut nodes = Vec::new();
-
-    // Check if there are square brackets in the string
-    if let Some(open_bracket) = value_from_output.find('[') {
-        if let Some(close_bracket) = value_from_output.find(']') {
-            let prefix = &value_from_output[..open_bracket];
-            let suffix = &value_from_output[close_bracket + 1..];
-            let range_content = &value_from_output[open_bracket + 1..close_bracket];
-
-            let real_prefix;
-            // Check if the prefix is also a list of nodes i.e., "myslumbox,node[39-40,42-43]"
-            if prefix.contains(',') {
-                let prefix_nodes: Vec<&str> = prefix.split(',').collect();
-                // Add all but the last value in `prefix_nodes` to the nodes list
-                nodes.extend(prefix_nodes.iter().take(prefix_nodes.len() - 1).cloned().map(|s| s.to_string()));
-                // Set `real_prefix` to the last value in the `prefix_nodes`
-                real_prefix = *prefix_nodes.last().unwrap();
-            } else {
-                real_prefix = prefix;
-            }
+/// Node counts of a partition by sinfo-style state. Every node is counted once, in the first
+/// of down, drain, alloc, mixed and idle that applies; anything else (future, unknown) is
+/// `other`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NodeCounts {
+    pub total: u32,
+    pub idle: u32,
+    pub mixed: u32,
+    pub alloc: u32,
+    pub drain: u32,
+    pub down: u32,
+    pub other: u32,
+}
 
-            // Split the range by comma
-            for part in range_content.split(',') {
-                if let Some(dash) = part.find('-') {
-                    let (start, end) = part.split_at(dash);
-                    let start = start.parse::<u32>().unwrap();
-                    let end = end[1..].parse::<u32>().unwrap();
-                    for i in start..=end {
-                        nodes.push(format!("{}{}", real_prefix, i));
-                    }
-                } else {
-                    nodes.push(format!("{}{}", real_prefix, part));
-                }
+impl NodeCounts {
+    fn count(&mut self, node: &Node) {
+        self.total += 1;
+        let Some(state) = &node.state else {
+            self.other += 1;
+            return;
+        };
+        let count = if state.is_down() || !state.is_responding() {
+            &mut self.down
+        } else if state.is_drain() {
+            &mut self.drain
+        } else {
+            match state.base {
+                BaseState::Idle => &mut self.idle,
+                BaseState::Mixed => &mut self.mixed,
+                BaseState::Allocated => &mut self.alloc,
+                _ => &mut self.other,
             }
+        };
+        *count += 1;
+    }
+}
 
-            if suffix.is_empty() {
-                return nodes;
-            }
+/// One line of `slurmtool partitions`: a partition's limits plus totals summed over its nodes.
+///
+/// The totals come from the node records rather than the partition's `TotalCPUs`, which
+/// counts nodes that are down. Free resources only count nodes that accept jobs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PartitionSummary {
+    pub name: String,
+    pub state: Option<String>,
+    pub default: bool,
+    pub hidden: bool,
+    pub default_time: Option<SlurmDuration>,
+    pub max_time: Option<SlurmDuration>,
+    pub nodes: NodeCounts,
+    pub cpus_total: u64,
+    pub cpus_free: u64,
+    pub mem_total: Memory,
+    pub mem_free: Memory,
+    pub gpus_total: u64,
+    pub gpus_free: u64,
+}
 
-            // If there is a suffix, remove the first comma 
-            // then check if the suffix is a list of nodes 
-            // i.e., "node[39-40,42-43],othernode,anothernode"
-            // should only add "othernode" and "anothernode" to the nodes list
-            let suffix = &suffix[1..];
-            if suffix.contains(',') {
-                nodes.extend(suffix.split(',').map(|s| s.to_string()));
-            } else {
-                nodes.push(suffix.to_string());
-            }
+impl PartitionSummary {
+    pub fn new(partition: &Partition, node_map: &NodeMap) -> Self {
+        let mut summary = PartitionSummary {
+            name: partition.name.clone(),
+            state: partition.state.clone(),
+            default: partition.default,
+            hidden: partition.hidden,
+            default_time: partition.default_time,
+            max_time: partition.max_time,
+            ..PartitionSummary::default()
+        };
 
+        let nodes = partition.nodes.iter().filter_map(|name| node_map.get(name));
+        for node in nodes.clone() {
+            summary.nodes.count(node);
+            summary.cpus_total += u64::from(node.cpu_total.unwrap_or(0));
+            summary.mem_total += node.real_memory.unwrap_or_default();
+            if node.state.as_ref().is_some_and(|s| s.is_available()) {
+                summary.cpus_free += u64::from(node.cpu_free().unwrap_or(0));
+                summary.mem_free += node.free_memory().unwrap_or_default();
+            }
         }
-    } else {
-        // Split by comma for cases like "myslumbox,node50"
-        nodes.extend(value_from_output.split(',').map(|s| s.to_string()));
+        let gpus = gres::inventory(nodes, "gpu");
+        summary.gpus_total = gpus.values().map(|totals| totals.total).sum();
+        summary.gpus_free = gpus.values().map(|totals| totals.free).sum();
+
+        summary
     }
+}
 
-    nodes
+// example value:
+// "node[39-40,42-43]"  -> ['node39', 'node40', 'node42', 'node43']
+// OR
+// "myslumbox,node[39-40,42-43]" -> ['myslumbox', 'node39', 'node40', 'node42', 'node43']
+//
+// See `hostlist::expand` for the full grammar.
+pub fn expand_node_range(value_from_output: &str) -> Result<Vec<String>, SlurmToolError> {
+    hostlist::expand(value_from_output)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::source::StaticSource;
 
     #[test]
     fn test_expand_node_range() {
@@ -315,7 +391,72 @@ mod tests {
         ];
 
         for (input, expected) in test_cases {
-            assert_eq!(expand_node_range(input), expected);
+            assert_eq!(expand_node_range(input).unwrap(), expected);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_partition_map_from_source() {
+        let source = StaticSource::new(
+            "PartitionName=debug Default=YES MaxTime=01:00:00 Nodes=node[1-2]\n\
+             NodeName=node1 Arch=x86_64\n\
+             PartitionName=gpu Default=NO Nodes=gpu1\n",
+        );
+
+        let partition_map = PartitionMap::build(&source).unwrap();
+
+        assert_eq!(partition_map.partitions.len(), 2);
+        let debug = partition_map.get("debug").unwrap();
+        assert!(debug.default);
+        assert_eq!(debug.nodes, vec!["node1", "node2"]);
+    }
+
+    #[test]
+    fn test_parse_partitions_oneliner() {
+        let stdout = "PartitionName=all AllowGroups=ALL AllowAccounts=lab1,lab2 AllowQos=ALL \
+            AllocNodes=ALL Default=YES QoS=N/A DefaultTime=NONE DisableRootJobs=NO ExclusiveUser=NO \
+            GraceTime=0 Hidden=NO MaxNodes=UNLIMITED MaxTime=7-00:00:00 MinNodes=0 LLN=NO \
+            MaxCPUsPerNode=UNLIMITED MaxCPUsPerSocket=UNLIMITED NodeSets=ALL Nodes=node[01-02] \
+            PriorityJobFactor=1 PriorityTier=1 RootOnly=NO ReqResv=NO OverSubscribe=NO \
+            OverTimeLimit=NONE PreemptMode=OFF State=UP TotalCPUs=64 TotalNodes=2 \
+            SelectTypeParameters=NONE JobDefaults=(null) DefMemPerNode=UNLIMITED \
+            MaxMemPerNode=UNLIMITED TRES=cpu=64,mem=500G,node=2,billing=64\n";
+
+        let partitions = Partition::parse_partitions(stdout).unwrap();
+
+        assert_eq!(partitions.len(), 1);
+        let all = &partitions[0];
+        assert_eq!(all.name, "all");
+        assert_eq!(all.allow_accounts, vec!["lab1", "lab2"]);
+        assert_eq!(all.max_time, Some(SlurmDuration::Finite(7 * 86400)));
+        assert_eq!(all.default_time, None);
+        assert!(all.allows_time("2-00:00:00".parse().unwrap()));
+        assert!(!all.allows_time(SlurmDuration::Unlimited));
+        assert_eq!(all.nodes, vec!["node01", "node02"]);
+        assert_eq!(all.total_cpus, Some(64));
+        assert_eq!(all.tres.as_ref().unwrap().cpu(), Some(64));
+        assert_eq!(all.tres.as_ref().unwrap().mem(), Some(Memory::new(500 * 1024)));
+    }
+
+    #[test]
+    fn test_partition_summary() {
+        let source = StaticSource::new(
+            "PartitionName=gpu Default=YES MaxTime=2-00:00:00 Nodes=gpu[01-03] State=UP TotalCPUs=192\n\
+             NodeName=gpu01 CPUTot=64 CPUAlloc=48 RealMemory=512000 AllocMem=256000 Gres=gpu:a100:4 GresUsed=gpu:a100:3(IDX:0-2) State=MIXED\n\
+             NodeName=gpu02 CPUTot=64 CPUAlloc=0 RealMemory=512000 AllocMem=0 Gres=gpu:v100:2 GresUsed=gpu:v100:0(IDX:N/A) State=IDLE+DRAIN\n\
+             NodeName=gpu03 CPUTot=64 CPUAlloc=0 RealMemory=512000 State=DOWN+NOT_RESPONDING\n",
+        );
+        let partitions = PartitionMap::build(&source).unwrap();
+        let nodes = NodeMap::build(&source).unwrap();
+        let summary = PartitionSummary::new(partitions.lookup("gpu").unwrap(), &nodes);
+
+        assert_eq!(
+            summary.nodes,
+            NodeCounts { total: 3, mixed: 1, drain: 1, down: 1, ..NodeCounts::default() }
+        );
+        assert_eq!((summary.cpus_total, summary.cpus_free), (192, 16));
+        assert_eq!(summary.mem_total, Memory::new(1536000));
+        assert_eq!(summary.mem_free, Memory::new(256000));
+        assert_eq!((summary.gpus_total, summary.gpus_free), (6, 1));
+    }
+}
@@ -0,0 +1,283 @@
+//! Quality of service definitions from `sacctmgr show qos`, and the limits a partition ends
+//! up enforcing once its partition QoS is applied.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use termcolor::Color;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::memory::Memory;
+use crate::partition::Partition;
+use crate::sacct;
+use crate::source::SlurmSource;
+use crate::table::{Cell, Value};
+use crate::tres::Tres;
+
+/// Columns `PartitionLimits::value` knows, for the `qos` table.
+pub const LIMIT_COLUMNS: &[&str] = &[
+    "partition", "qos", "max_time", "max_nodes", "max_cpus_per_node", "max_mem_per_node",
+    "max_tres_per_job", "max_tres_per_user", "max_jobs_per_user", "grp_tres", "priority",
+    "preempt",
+];
+
+#[derive(Debug, Default)]
+pub struct QosMap {
+    pub qos: BTreeMap<String, Qos>,
+}
+
+impl QosMap {
+    pub fn build(source: &dyn SlurmSource) -> Result<Self, SlurmToolError> {
+        let qos_list = Qos::fetch_and_parse_qos(source)?;
+        let mut qos_map = QosMap::default();
+
+        for qos in qos_list {
+            // make sure the QoS name is unique
+            if qos_map.qos.contains_key(&qos.name) {
+                return Err(SlurmToolError::Duplicate { kind: "qos", name: qos.name });
+            }
+
+            qos_map.qos.insert(qos.name.clone(), qos);
+        }
+
+        Ok(qos_map)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Qos> {
+        self.qos.get(name)
+    }
+}
+
+/// One QoS. Unset limits are `None`; sacctmgr prints them as empty fields.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Qos {
+    pub name: String,
+    pub priority: Option<u32>,
+    /// QoS whose jobs this one may preempt.
+    pub preempt: Vec<String>,
+    pub preempt_mode: Option<String>,
+    pub flags: Vec<String>,
+    pub grp_tres: Option<Tres>,
+    pub grp_jobs: Option<u32>,
+    pub max_wall: Option<SlurmDuration>,
+    /// `MaxTRES`, per job.
+    pub max_tres_per_job: Option<Tres>,
+    pub max_tres_per_node: Option<Tres>,
+    pub max_tres_per_user: Option<Tres>,
+    pub max_jobs_per_user: Option<u32>,
+    pub max_submit_per_user: Option<u32>,
+    pub max_tres_per_account: Option<Tres>,
+    pub max_jobs_per_account: Option<u32>,
+    pub min_tres: Option<Tres>,
+}
+
+impl Qos {
+    /// Accepts the abbreviated headers sacctmgr prints (`MaxTRESPU`) as well as the long
+    /// names accepted by `--format` (`MaxTRESPerUser`).
+    pub fn from_fields(fields: &[(&str, &str)]) -> Self {
+        let mut qos = Qos::default();
+        let tres = |value: &str| value.parse().ok().filter(|tres: &Tres| !tres.is_empty());
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').filter(|item| !item.is_empty()).map(String::from).collect()
+        };
+
+        for (key, value) in fields {
+            match *key {
+                "Name" => qos.name = value.to_string(),
+                "Priority" => qos.priority = value.parse().ok(),
+                "Preempt" => qos.preempt = list(value),
+                "PreemptMode" => qos.preempt_mode = Some(value.to_string()).filter(|mode| !mode.is_empty()),
+                "Flags" => qos.flags = list(value),
+                "GrpTRES" => qos.grp_tres = tres(value),
+                "GrpJobs" => qos.grp_jobs = value.parse().ok(),
+                "MaxWall" => qos.max_wall = SlurmDuration::parse_optional(value).ok().flatten(),
+                "MaxTRES" | "MaxTRESPerJob" => qos.max_tres_per_job = tres(value),
+                "MaxTRESPerNode" => qos.max_tres_per_node = tres(value),
+                "MaxTRESPU" | "MaxTRESPerUser" => qos.max_tres_per_user = tres(value),
+                "MaxJobsPU" | "MaxJobsPerUser" => qos.max_jobs_per_user = value.parse().ok(),
+                "MaxSubmitPU" | "MaxSubmitJobsPerUser" => qos.max_submit_per_user = value.parse().ok(),
+                "MaxTRESPA" | "MaxTRESPerAccount" => qos.max_tres_per_account = tres(value),
+                "MaxJobsPA" | "MaxJobsPerAccount" => qos.max_jobs_per_account = value.parse().ok(),
+                "MinTRES" | "MinTRESPerJob" => qos.min_tres = tres(value),
+                _ => {} // Ignore any unknown fields
+            }
+        }
+
+        qos
+    }
+
+    /// Fetch the QoS records from `source` and parse them into a vector of `Qos` structs.
+    pub fn fetch_and_parse_qos(source: &dyn SlurmSource) -> Result<Vec<Self>, SlurmToolError> {
+        let stdout = source.sacctmgr_qos()?;
+        Qos::parse_qos(&stdout)
+    }
+
+    /// Parse `sacctmgr --parsable2 show qos` output, header line included.
+    pub fn parse_qos(stdout: &str) -> Result<Vec<Self>, SlurmToolError> {
+        Ok(sacct::parsable_records(stdout, "Name")?
+            .iter()
+            .map(|fields| Qos::from_fields(fields))
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitSource {
+    Qos,
+    Partition,
+}
+
+/// A limit and where it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Limit<T> {
+    pub value: T,
+    pub source: LimitSource,
+}
+
+/// The limits a partition enforces. A limit set in the partition QoS takes precedence over
+/// the partition's own, as in Slurm's limit hierarchy; per-user and group limits only exist
+/// in the QoS.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionLimits {
+    pub partition: String,
+    pub qos: Option<String>,
+    pub max_time: Option<Limit<SlurmDuration>>,
+    pub max_nodes: Option<Limit<u64>>,
+    pub max_cpus_per_node: Option<Limit<u64>>,
+    pub max_mem_per_node: Option<Limit<Memory>>,
+    pub max_tres_per_job: Option<Tres>,
+    pub max_tres_per_user: Option<Tres>,
+    pub max_jobs_per_user: Option<u32>,
+    pub grp_tres: Option<Tres>,
+    pub priority: Option<u32>,
+    pub preempt: Vec<String>,
+}
+
+impl PartitionLimits {
+    /// Combines `partition`'s limits with those of its partition QoS, if `qos_map` has it.
+    pub fn new(partition: &Partition, qos_map: &QosMap) -> Self {
+        let qos = partition.qos.as_deref().and_then(|name| {
+            let qos = qos_map.get(name);
+            if qos.is_none() {
+                log::warn!("Partition {} uses QoS {}, which sacctmgr does not list", partition.name, name);
+            }
+            qos
+        });
+        let per_job = |key: &str| qos.and_then(|q| q.max_tres_per_job.as_ref()).and_then(|tres| tres.get(key));
+        let per_node = |key: &str| qos.and_then(|q| q.max_tres_per_node.as_ref()).and_then(|tres| tres.get(key));
+        let partition_max_cpus = partition.max_cpus_per_node.as_deref().and_then(|value| value.parse().ok());
+
+        PartitionLimits {
+            partition: partition.name.clone(),
+            qos: partition.qos.clone(),
+            max_time: prefer_qos(qos.and_then(|q| q.max_wall), partition.max_time),
+            max_nodes: prefer_qos(per_job("node"), partition.max_nodes.map(u64::from)),
+            max_cpus_per_node: prefer_qos(per_node("cpu"), partition_max_cpus),
+            max_mem_per_node: prefer_qos(per_node("mem").map(Memory::new), partition.max_mem_per_node),
+            max_tres_per_job: qos.and_then(|q| q.max_tres_per_job.clone()),
+            max_tres_per_user: qos.and_then(|q| q.max_tres_per_user.clone()),
+            max_jobs_per_user: qos.and_then(|q| q.max_jobs_per_user),
+            grp_tres: qos.and_then(|q| q.grp_tres.clone()),
+            priority: qos.and_then(|q| q.priority),
+            preempt: qos.map(|q| q.preempt.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// The value of one of `LIMIT_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        let tres = |value: Option<&Tres>| Value::from_option(value, |t| Value::Text(t.to_string()));
+
+        match column {
+            "partition" => Value::Text(self.partition.clone()),
+            "qos" => Value::from_option(self.qos.clone(), Value::Text),
+            "max_time" => Value::from_option(self.max_time, |limit| Value::Duration(limit.value)),
+            "max_nodes" => Value::from_option(self.max_nodes, |limit| Value::Count(limit.value)),
+            "max_cpus_per_node" => Value::from_option(self.max_cpus_per_node, |limit| Value::Count(limit.value)),
+            "max_mem_per_node" => Value::from_option(self.max_mem_per_node, |limit| Value::Memory(limit.value)),
+            "max_tres_per_job" => tres(self.max_tres_per_job.as_ref()),
+            "max_tres_per_user" => tres(self.max_tres_per_user.as_ref()),
+            "max_jobs_per_user" => Value::from_option(self.max_jobs_per_user, |n| Value::Count(n.into())),
+            "grp_tres" => tres(self.grp_tres.as_ref()),
+            "priority" => Value::from_option(self.priority, |n| Value::Count(n.into())),
+            "preempt" if self.preempt.is_empty() => Value::Empty,
+            "preempt" => Value::Text(self.preempt.join(",")),
+            _ => Value::Empty,
+        }
+    }
+
+    /// Where the limit in `column` comes from; `None` for unset limits and columns that only
+    /// ever come from one place.
+    pub fn source(&self, column: &str) -> Option<LimitSource> {
+        match column {
+            "max_time" => self.max_time.map(|limit| limit.source),
+            "max_nodes" => self.max_nodes.map(|limit| limit.source),
+            "max_cpus_per_node" => self.max_cpus_per_node.map(|limit| limit.source),
+            "max_mem_per_node" => self.max_mem_per_node.map(|limit| limit.source),
+            _ => None,
+        }
+    }
+
+    /// Like `value`, with limits that the partition QoS overrides coloured.
+    pub fn cell(&self, column: &str) -> Cell {
+        match self.source(column) {
+            Some(LimitSource::Qos) => Cell::colored(self.value(column), Color::Cyan),
+            _ => Cell::new(self.value(column)),
+        }
+    }
+}
+
+fn prefer_qos<T>(qos: Option<T>, partition: Option<T>) -> Option<Limit<T>> {
+    match (qos, partition) {
+        (Some(value), _) => Some(Limit { value, source: LimitSource::Qos }),
+        (None, Some(value)) => Some(Limit { value, source: LimitSource::Partition }),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition::PartitionMap;
+    use crate::source::StaticSource;
+
+    const DUMP: &str = "\
+PartitionName=gpu Nodes=gpu01 QoS=gpu MaxTime=7-00:00:00 MaxNodes=4 MaxMemPerNode=512000 State=UP
+PartitionName=cpu Nodes=node01 QoS=N/A MaxTime=UNLIMITED MaxCPUsPerNode=32 State=UP
+Name|Priority|GraceTime|Preempt|PreemptExemptTime|PreemptMode|Flags|UsageThres|UsageFactor|GrpTRES|GrpTRESMins|GrpTRESRunMins|GrpJobs|GrpSubmit|GrpWall|MaxTRES|MaxTRESPerNode|MaxTRESMins|MaxWall|MaxTRESPU|MaxJobsPU|MaxSubmitPU|MaxTRESPA|MaxJobsPA|MaxSubmitPA|MinTRES
+normal|0|00:00:00|||cluster|||1.000000|||||||||||||||||
+gpu|100|00:00:00|normal,scavenger||cluster|DenyOnLimit||1.000000|gres/gpu=32|||||||cpu=64,mem=256000M||2-00:00:00|gres/gpu=8|10|||||
+";
+
+    #[test]
+    fn test_partition_limits() {
+        let source = StaticSource::new(DUMP);
+        let qos_map = QosMap::build(&source).unwrap();
+        assert_eq!(qos_map.qos.len(), 2);
+
+        let gpu_qos = qos_map.get("gpu").unwrap();
+        assert_eq!(gpu_qos.priority, Some(100));
+        assert_eq!(gpu_qos.preempt, vec!["normal", "scavenger"]);
+        assert_eq!(gpu_qos.max_jobs_per_user, Some(10));
+        assert_eq!(gpu_qos.max_tres_per_user.as_ref().unwrap().gres("gpu"), Some(8));
+        assert_eq!(qos_map.get("normal").unwrap().max_wall, None);
+
+        let partitions = PartitionMap::build(&source).unwrap();
+        let gpu = PartitionLimits::new(partitions.get("gpu").unwrap(), &qos_map);
+        assert_eq!(
+            gpu.max_time,
+            Some(Limit { value: SlurmDuration::from_secs(2 * 86400), source: LimitSource::Qos })
+        );
+        assert_eq!(gpu.max_nodes, Some(Limit { value: 4, source: LimitSource::Partition }));
+        assert_eq!(gpu.value("max_mem_per_node"), Value::Memory("250G".parse().unwrap()));
+        assert_eq!(gpu.source("max_mem_per_node"), Some(LimitSource::Qos));
+        assert_eq!(gpu.value("grp_tres"), Value::Text("gres/gpu=32".to_string()));
+
+        let cpu = PartitionLimits::new(partitions.get("cpu").unwrap(), &qos_map);
+        assert_eq!(cpu.qos, None);
+        assert_eq!(cpu.max_time, Some(Limit { value: SlurmDuration::Unlimited, source: LimitSource::Partition }));
+        assert_eq!(cpu.value("max_cpus_per_node"), Value::Count(32));
+        assert_eq!(cpu.value("priority"), Value::Empty);
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use termcolor::Color;
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::hostlist;
+use crate::json;
+use crate::oneliner::{self, not_null};
+use crate::source::{Entity, SlurmSource};
+use crate::table::{Cell, Value};
+use crate::timestamp::{self, parse_timestamp};
+
+/// Columns `Reservation::value` knows, for the `reservations` table.
+pub const RESERVATION_COLUMNS: &[&str] = &[
+    "name", "state", "start_time", "end_time", "duration", "starts_in", "nodes", "nodelist",
+    "cores", "partition", "users", "accounts", "flags",
+];
+
+/// Every key `scontrol show reservation --oneliner` may print, including the ones
+/// `Reservation` ignores. See `node::NODE_KEYS` for why the list has to be complete.
+pub const RESERVATION_KEYS: &[&str] = &[
+    "ReservationName", "StartTime", "EndTime", "Duration", "Nodes", "NodeCnt", "CoreCnt",
+    "Features", "PartitionName", "Flags", "NodeName", "CoreIDs", "TRES", "Users", "Groups",
+    "Accounts", "Licenses", "State", "BurstBuffer", "Watts", "MaxStartDelay", "Comment",
+    "Skip",
+];
+
+#[derive(Debug, Default)]
+pub struct ReservationMap {
+    pub reservations: BTreeMap<String, Reservation>,
+}
+
+impl ReservationMap {
+    pub fn build(source: &dyn SlurmSource) -> Result<Self, SlurmToolError> {
+        let reservations = Reservation::fetch_and_parse_reservations(source)?;
+        let mut reservation_map = ReservationMap::default();
+
+        for reservation in reservations {
+            // make sure the reservation name is unique
+            if reservation_map.reservations.contains_key(&reservation.name) {
+                return Err(SlurmToolError::Duplicate { kind: "reservation", name: reservation.name });
+            }
+
+            reservation_map.reservations.insert(reservation.name.clone(), reservation);
+        }
+
+        Ok(reservation_map)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Reservation> {
+        self.reservations.get(name)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Reservation {
+    pub name: String,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub end_time: Option<DateTime<Local>>,
+    pub duration: Option<SlurmDuration>,
+    /// The reserved nodes, expanded.
+    pub nodes: Vec<String>,
+    pub node_count: Option<u32>,
+    pub core_count: Option<u32>,
+    pub partition: Option<String>,
+    pub features: Option<String>,
+    /// `MAINT`, `IGNORE_JOBS`, `DAILY`, ...
+    pub flags: Vec<String>,
+    /// Who may use the reservation; entries starting with `-` are denied instead.
+    pub users: Vec<String>,
+    pub accounts: Vec<String>,
+    /// `ACTIVE` or `INACTIVE`.
+    pub state: Option<String>,
+}
+
+impl Reservation {
+    pub fn from_fields(fields: &[(&str, &str)]) -> Self {
+        let mut reservation = Reservation::default();
+        let list = |value: &str| -> Vec<String> {
+            match not_null(value) {
+                Some(value) => value.split(',').map(String::from).collect(),
+                None => Vec::new(),
+            }
+        };
+
+        for (key, value) in fields {
+            match *key {
+                "ReservationName" => reservation.name = value.to_string(),
+                "StartTime" => reservation.start_time = parse_timestamp(value).ok().flatten(),
+                "EndTime" => reservation.end_time = parse_timestamp(value).ok().flatten(),
+                "Duration" => reservation.duration = SlurmDuration::parse_optional(value).ok().flatten(),
+                "Nodes" => {
+                    if let Some(nodes) = not_null(value) {
+                        reservation.nodes = hostlist::expand(&nodes).unwrap_or_else(|e| {
+                            log::warn!("Ignoring node list of reservation '{}': {}", reservation.name, e);
+                            Vec::new()
+                        });
+                    }
+                }
+                "NodeCnt" => reservation.node_count = value.parse().ok(),
+                "CoreCnt" => reservation.core_count = value.parse().ok(),
+                "PartitionName" => reservation.partition = not_null(value),
+                "Features" => reservation.features = not_null(value),
+                "Flags" => reservation.flags = list(value),
+                "Users" => reservation.users = list(value),
+                "Accounts" => reservation.accounts = list(value),
+                "State" => reservation.state = not_null(value),
+                _ => {} // Ignore any unknown keys
+            }
+        }
+
+        reservation
+    }
+
+    /// Fetch the reservation records from `source` and parse them into a vector of
+    /// `Reservation` structs.
+    pub fn fetch_and_parse_reservations(source: &dyn SlurmSource) -> Result<Vec<Self>, SlurmToolError> {
+        let stdout = source.show(Entity::Reservation)?;
+        Reservation::parse_reservations(&stdout)
+    }
+
+    /// Parse `scontrol show reservation` output into a vector of `Reservation` structs.
+    ///
+    /// Accepts both `--json` documents and `--oneliner` records. scontrol's "No reservations
+    /// in the system" yields an empty list.
+    pub fn parse_reservations(stdout: &str) -> Result<Vec<Self>, SlurmToolError> {
+        if json::is_json(stdout) {
+            return json::parse_reservations(stdout);
+        }
+
+        let mut reservations = Vec::new();
+
+        for (number, line) in stdout.lines().enumerate() {
+            let fields = oneliner::tokenize(line, RESERVATION_KEYS);
+
+            if fields.is_empty() {
+                continue;
+            }
+            oneliner::check_record(&fields, "ReservationName", number + 1)?;
+
+            reservations.push(Reservation::from_fields(&fields));
+        }
+
+        Ok(reservations)
+    }
+
+    /// Maintenance reservations (`Flags=MAINT`) mark their nodes `MAINT` while active.
+    pub fn is_maintenance(&self) -> bool {
+        is_maintenance(&self.flags)
+    }
+
+    pub fn has_node(&self, node: &str) -> bool {
+        self.nodes.iter().any(|name| name == node)
+    }
+
+    pub fn has_ended(&self, now: &DateTime<Local>) -> bool {
+        self.end_time.is_some_and(|end| end <= *now)
+    }
+
+    /// Time until the reservation starts; `None` once it has started.
+    pub fn starts_in(&self, now: &DateTime<Local>) -> Option<SlurmDuration> {
+        starts_in(self.start_time, now)
+    }
+
+    /// The value of one of `RESERVATION_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str, now: &DateTime<Local>) -> Value {
+        let join = |items: &[String]| {
+            if items.is_empty() { Value::Empty } else { Value::Text(items.join(",")) }
+        };
+        let time = |value: Option<DateTime<Local>>| {
+            Value::from_option(value, |t| Value::Text(timestamp::format_timestamp(&t)))
+        };
+        let count = |value: Option<u32>| Value::from_option(value, |v| Value::Count(v.into()));
+
+        match column {
+            "name" => Value::Text(self.name.clone()),
+            "state" => Value::from_option(self.state.clone(), Value::Text),
+            "start_time" => time(self.start_time),
+            "end_time" => time(self.end_time),
+            "duration" => Value::from_option(self.duration, Value::Duration),
+            "starts_in" => Value::from_option(self.starts_in(now), Value::Duration),
+            "nodes" => count(self.node_count.or(Some(self.nodes.len() as u32))),
+            "nodelist" if self.nodes.is_empty() => Value::Empty,
+            "nodelist" => Value::Text(hostlist::compress(&self.nodes)),
+            "cores" => count(self.core_count),
+            "partition" => Value::from_option(self.partition.clone(), Value::Text),
+            "users" => join(&self.users),
+            "accounts" => join(&self.accounts),
+            "flags" => join(&self.flags),
+            _ => Value::Empty,
+        }
+    }
+
+    /// Like `value`, with maintenance reservations' names and active states highlighted.
+    pub fn cell(&self, column: &str, now: &DateTime<Local>) -> Cell {
+        let value = self.value(column, now);
+        match column {
+            "name" if self.is_maintenance() => Cell::colored(value, Color::Magenta),
+            "state" if self.state.as_deref() == Some("ACTIVE") => Cell::colored(value, Color::Cyan),
+            _ => Cell::new(value),
+        }
+    }
+
+    /// The part of the reservation a node needs to know about.
+    pub fn node_share(&self) -> NodeReservation {
+        NodeReservation {
+            name: self.name.clone(),
+            start_time: self.start_time,
+            end_time: self.end_time,
+            flags: self.flags.clone(),
+        }
+    }
+}
+
+/// A current or upcoming reservation of a node, as joined onto the node by
+/// `NodeMap::attach_reservations`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeReservation {
+    pub name: String,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub end_time: Option<DateTime<Local>>,
+    pub flags: Vec<String>,
+}
+
+impl NodeReservation {
+    pub fn is_maintenance(&self) -> bool {
+        is_maintenance(&self.flags)
+    }
+
+    /// Time until the reservation starts; `None` once it has started.
+    pub fn starts_in(&self, now: &DateTime<Local>) -> Option<SlurmDuration> {
+        starts_in(self.start_time, now)
+    }
+}
+
+fn is_maintenance(flags: &[String]) -> bool {
+    flags.iter().any(|flag| flag == "MAINT")
+}
+
+fn starts_in(start_time: Option<DateTime<Local>>, now: &DateTime<Local>) -> Option<SlurmDuration> {
+    start_time
+        .filter(|start| start > now)
+        .map(|start| timestamp::elapsed(now, &start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StaticSource;
+
+    const DUMP: &str = "\
+ReservationName=maint StartTime=2024-02-01T08:00:00 EndTime=2024-02-01T20:00:00 Duration=12:00:00 Nodes=node[01-04] NodeCnt=4 CoreCnt=128 Features=(null) PartitionName=(null) Flags=MAINT,IGNORE_JOBS,SPEC_NODES TRES=cpu=128 Users=root Groups=(null) Accounts=(null) Licenses=(null) State=INACTIVE BurstBuffer=(null) Watts=n/a MaxStartDelay=(null)
+ReservationName=course StartTime=2024-01-01T00:00:00 EndTime=2024-03-01T00:00:00 Duration=60-00:00:00 Nodes=gpu01 NodeCnt=1 CoreCnt=64 Features=(null) PartitionName=gpu Flags=SPEC_NODES TRES=cpu=64 Users=(null) Groups=(null) Accounts=teaching,-physics Licenses=(null) State=ACTIVE BurstBuffer=(null) Watts=n/a MaxStartDelay=(null)
+";
+
+    #[test]
+    fn test_parse_reservations() {
+        let reservations = ReservationMap::build(&StaticSource::new(DUMP)).unwrap();
+        assert_eq!(reservations.reservations.len(), 2);
+
+        let maint = reservations.get("maint").unwrap();
+        assert!(maint.is_maintenance());
+        assert_eq!(maint.nodes, vec!["node01", "node02", "node03", "node04"]);
+        assert_eq!(maint.users, vec!["root"]);
+        assert!(maint.accounts.is_empty());
+        assert_eq!(maint.partition, None);
+        assert_eq!(maint.duration, Some(SlurmDuration::from_secs(12 * 3600)));
+
+        let now = parse_timestamp("2024-01-31T08:00:00").unwrap().unwrap();
+        assert_eq!(maint.value("starts_in", &now), Value::Duration(SlurmDuration::from_secs(86400)));
+        assert!(!maint.has_ended(&now));
+
+        let course = reservations.get("course").unwrap();
+        assert_eq!(course.accounts, vec!["teaching", "-physics"]);
+        assert_eq!(course.starts_in(&now), None);
+        assert!(course.has_node("gpu01"));
+
+        assert!(Reservation::parse_reservations("No reservations in the system\n").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+//! Finished (and running) jobs from the accounting database, via `sacct --parsable2`.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer};
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::hostlist;
+use crate::job_state::JobState;
+use crate::memory::Memory;
+use crate::source::SlurmSource;
+use crate::table::{Cell, Value};
+use crate::timestamp::{self, parse_timestamp};
+use crate::tres::Tres;
+
+/// The fields requested with `--format`; `parse_sacct` reads columns by header name, so any
+/// subset or order works too.
+pub const SACCT_FIELDS: &[&str] = &[
+    "JobID", "JobName", "User", "Account", "Partition", "State", "ExitCode", "Elapsed", "TotalCPU",
+    "MaxRSS", "ReqMem", "ReqTRES", "AllocTRES", "NNodes", "NCPUS", "Timelimit", "Submit", "Start",
+    "End", "NodeList",
+];
+
+/// Columns `AccountingRecord::value` knows, for the `history` table.
+pub const HISTORY_COLUMNS: &[&str] = &[
+    "job_id", "name", "user", "account", "partition", "state", "exit_code", "elapsed",
+    "time_limit", "total_cpu", "max_rss", "nodes", "cpus", "req_mem", "req_tres", "alloc_tres",
+    "nodelist", "submit_time", "start_time", "end_time",
+];
+
+/// `ExitCode` as sacct prints it: the exit status and, if the process was killed, the signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitCode {
+    pub code: u32,
+    pub signal: u32,
+}
+
+impl ExitCode {
+    pub fn is_success(&self) -> bool {
+        self.code == 0 && self.signal == 0
+    }
+}
+
+impl FromStr for ExitCode {
+    type Err = String;
+
+    /// Parses `code:signal`, e.g. `0:0` or `0:15`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid exit code '{}'", value);
+        let (code, signal) = value.trim().split_once(':').ok_or_else(invalid)?;
+        Ok(ExitCode {
+            code: code.parse().map_err(|_| invalid())?,
+            signal: signal.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.code, self.signal)
+    }
+}
+
+impl Serialize for ExitCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// `ReqMem` as sacct prints it. Before Slurm 21.08 the value carries a `c` (per CPU) or `n`
+/// (per node) suffix; newer versions print the job's total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "per", content = "memory", rename_all = "lowercase")]
+pub enum MemoryRequest {
+    Cpu(Memory),
+    Node(Memory),
+    Job(Memory),
+}
+
+impl FromStr for MemoryRequest {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(memory) = value.strip_suffix('c') {
+            Ok(MemoryRequest::Cpu(memory.parse()?))
+        } else if let Some(memory) = value.strip_suffix('n') {
+            Ok(MemoryRequest::Node(memory.parse()?))
+        } else {
+            Ok(MemoryRequest::Job(value.parse()?))
+        }
+    }
+}
+
+/// One line of sacct output: a job allocation or one of its steps.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct AccountingRecord {
+    /// The job part of `JobID`: `1234`, `1234_5` for an array task.
+    pub job_id: String,
+    /// The step part of `JobID` (`batch`, `extern`, `0`, ...); `None` for the allocation.
+    pub step: Option<String>,
+    pub name: Option<String>,
+    /// Steps inherit user, account and partition from their allocation.
+    pub user: Option<String>,
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    pub state: Option<JobState>,
+    pub exit_code: Option<ExitCode>,
+    pub elapsed: Option<SlurmDuration>,
+    /// CPU time used by all tasks, user plus system.
+    pub total_cpu: Option<SlurmDuration>,
+    /// The peak resident memory of the largest task; only steps report it.
+    pub max_rss: Option<Memory>,
+    pub req_mem: Option<MemoryRequest>,
+    pub req_tres: Option<Tres>,
+    pub alloc_tres: Option<Tres>,
+    pub num_nodes: Option<u32>,
+    pub num_cpus: Option<u32>,
+    pub time_limit: Option<SlurmDuration>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub submit_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(serialize_with = "timestamp::serialize_optional")]
+    pub end_time: Option<DateTime<Local>>,
+    /// The allocated nodes, expanded.
+    pub nodes: Vec<String>,
+}
+
+impl AccountingRecord {
+    pub fn from_fields(fields: &[(&str, &str)]) -> Self {
+        let mut record = AccountingRecord::default();
+        let text = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+
+        for (key, value) in fields {
+            match *key {
+                "JobID" => {
+                    let (job_id, step) = value.split_once('.').map_or((*value, None), |(id, step)| (id, Some(step)));
+                    record.job_id = job_id.to_string();
+                    record.step = step.map(String::from);
+                }
+                "JobName" => record.name = text(value),
+                "User" => record.user = text(value),
+                "Account" => record.account = text(value),
+                "Partition" => record.partition = text(value),
+                "State" => record.state = value.parse().ok(),
+                "ExitCode" => record.exit_code = value.parse().ok(),
+                "Elapsed" => record.elapsed = SlurmDuration::parse_optional(value).ok().flatten(),
+                "TotalCPU" => record.total_cpu = SlurmDuration::parse_optional(value).ok().flatten(),
+                "MaxRSS" => record.max_rss = value.parse().ok(),
+                "ReqMem" => record.req_mem = value.parse().ok(),
+                "ReqTRES" => record.req_tres = value.parse().ok().filter(|tres: &Tres| !tres.is_empty()),
+                "AllocTRES" => record.alloc_tres = value.parse().ok().filter(|tres: &Tres| !tres.is_empty()),
+                "NNodes" => record.num_nodes = value.parse().ok(),
+                "NCPUS" => record.num_cpus = value.parse().ok(),
+                "Timelimit" => record.time_limit = SlurmDuration::parse_optional(value).ok().flatten(),
+                "Submit" => record.submit_time = parse_timestamp(value).ok().flatten(),
+                "Start" => record.start_time = parse_timestamp(value).ok().flatten(),
+                "End" => record.end_time = parse_timestamp(value).ok().flatten(),
+                "NodeList" if value.is_empty() || value.starts_with("None") => {}
+                "NodeList" => {
+                    record.nodes = hostlist::expand(value).unwrap_or_else(|e| {
+                        log::warn!("Ignoring node list of job {}: {}", record.job_id, e);
+                        Vec::new()
+                    });
+                }
+                _ => {} // Ignore any unknown fields
+            }
+        }
+
+        record
+    }
+
+    /// The id as sacct prints it, e.g. `1234.batch`.
+    pub fn id(&self) -> String {
+        match &self.step {
+            Some(step) => format!("{}.{}", self.job_id, step),
+            None => self.job_id.clone(),
+        }
+    }
+
+    pub fn is_step(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// The memory requested for the whole job, from `ReqMem` or else `ReqTRES`.
+    pub fn requested_memory(&self) -> Option<Memory> {
+        match self.req_mem {
+            Some(MemoryRequest::Cpu(memory)) => self.num_cpus.map(|cpus| memory * u64::from(cpus)),
+            Some(MemoryRequest::Node(memory)) => self.num_nodes.map(|nodes| memory * u64::from(nodes)),
+            Some(MemoryRequest::Job(memory)) => Some(memory),
+            None => self.req_tres.as_ref().and_then(Tres::mem),
+        }
+    }
+
+    /// The memory requested on each node, assuming CPUs are spread evenly over the nodes.
+    pub fn requested_memory_per_node(&self) -> Option<Memory> {
+        let nodes = u64::from(self.num_nodes.unwrap_or(1).max(1));
+        match self.req_mem {
+            Some(MemoryRequest::Node(memory)) => Some(memory),
+            _ => self.requested_memory().map(|memory| Memory::from_bytes(memory.as_bytes() / nodes)),
+        }
+    }
+
+    /// The value of one of `HISTORY_COLUMNS`; unknown columns are `Value::Empty`.
+    pub fn value(&self, column: &str) -> Value {
+        let text = |value: Option<&String>| Value::from_option(value.cloned(), Value::Text);
+        let time = |value: Option<DateTime<Local>>| {
+            Value::from_option(value, |t| Value::Text(timestamp::format_timestamp(&t)))
+        };
+        let tres = |value: Option<&Tres>| Value::from_option(value, |t| Value::Text(t.to_string()));
+
+        match column {
+            "job_id" => Value::Text(self.id()),
+            "name" => text(self.name.as_ref()),
+            "user" => text(self.user.as_ref()),
+            "account" => text(self.account.as_ref()),
+            "partition" => text(self.partition.as_ref()),
+            "state" => Value::from_option(self.state.as_ref(), |s| Value::Text(s.to_string())),
+            "exit_code" => Value::from_option(self.exit_code, |e| Value::Text(e.to_string())),
+            "elapsed" => Value::from_option(self.elapsed, Value::Duration),
+            "time_limit" => Value::from_option(self.time_limit, Value::Duration),
+            "total_cpu" => Value::from_option(self.total_cpu, Value::Duration),
+            "max_rss" => Value::from_option(self.max_rss, Value::Memory),
+            "nodes" => Value::from_option(self.num_nodes, |n| Value::Count(n.into())),
+            "cpus" => Value::from_option(self.num_cpus, |n| Value::Count(n.into())),
+            "req_mem" => Value::from_option(self.requested_memory(), Value::Memory),
+            "req_tres" => tres(self.req_tres.as_ref()),
+            "alloc_tres" => tres(self.alloc_tres.as_ref()),
+            "nodelist" if self.nodes.is_empty() => Value::Empty,
+            "nodelist" => Value::Text(hostlist::compress(&self.nodes)),
+            "submit_time" => time(self.submit_time),
+            "start_time" => time(self.start_time),
+            "end_time" => time(self.end_time),
+            _ => Value::Empty,
+        }
+    }
+
+    /// Like `value`, with the state column coloured by state.
+    pub fn cell(&self, column: &str) -> Cell {
+        match (column, &self.state) {
+            ("state", Some(state)) => Cell::colored(self.value(column), state.color()),
+            _ => Cell::new(self.value(column)),
+        }
+    }
+}
+
+/// Parse `sacct --parsable2` output, header line included, into records.
+///
+/// Steps follow their allocation in sacct's output and inherit its user, account and
+/// partition, which sacct leaves empty for steps.
+pub fn parse_sacct(stdout: &str) -> Result<Vec<AccountingRecord>, SlurmToolError> {
+    let mut records: Vec<AccountingRecord> = Vec::new();
+
+    for fields in parsable_records(stdout, "JobID")? {
+        let mut record = AccountingRecord::from_fields(&fields);
+        if record.is_step() {
+            if let Some(allocation) = records.iter().rev().find(|r| !r.is_step() && r.job_id == record.job_id) {
+                record.user = record.user.or_else(|| allocation.user.clone());
+                record.account = record.account.or_else(|| allocation.account.clone());
+                record.partition = record.partition.or_else(|| allocation.partition.clone());
+            }
+        }
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Splits `--parsable2` output of sacct or sacctmgr into `(header, value)` pairs, one list
+/// per record. Lines starting with `header_key|` are headers and name the fields of the
+/// lines after them.
+pub fn parsable_records<'a>(
+    stdout: &'a str,
+    header_key: &str,
+) -> Result<Vec<Vec<(&'a str, &'a str)>>, SlurmToolError> {
+    let mut records = Vec::new();
+    let mut header: Option<Vec<&str>> = None;
+
+    for (number, line) in stdout.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.split('|').collect();
+        if values[0] == header_key {
+            header = Some(values);
+            continue;
+        }
+
+        let parse_error = |message: String| SlurmToolError::Parse { line: Some(number + 1), field: None, message };
+        let header = header
+            .as_ref()
+            .ok_or_else(|| parse_error(format!("No {}|... header line before the first record", header_key)))?;
+        if values.len() != header.len() {
+            return Err(parse_error(format!("Expected {} fields, found {}", header.len(), values.len())));
+        }
+
+        records.push(header.iter().copied().zip(values).collect());
+    }
+
+    Ok(records)
+}
+
+/// Which jobs to fetch from the accounting database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryQuery {
+    /// Only these jobs (or every task of these arrays), whenever they ran; the time window
+    /// is ignored.
+    pub jobs: Vec<String>,
+    /// Only these users; empty means the current user unless `all_users` is set.
+    pub users: Vec<String>,
+    pub all_users: bool,
+    pub accounts: Vec<String>,
+    /// State names or squeue codes; empty matches every state.
+    pub states: Vec<String>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Leave out job steps.
+    pub allocations_only: bool,
+}
+
+impl HistoryQuery {
+    /// The `sacct` command line for this query.
+    pub fn sacct_args(&self) -> Vec<String> {
+        let mut args = vec!["--parsable2".to_string(), format!("--format={}", SACCT_FIELDS.join(","))];
+        if self.jobs.is_empty() {
+            args.push(format!("--starttime={}", timestamp::format_timestamp(&self.start)));
+            args.push(format!("--endtime={}", timestamp::format_timestamp(&self.end)));
+        } else {
+            args.push(format!("--jobs={}", self.jobs.join(",")));
+        }
+        if !self.users.is_empty() {
+            args.push(format!("--user={}", self.users.join(",")));
+        } else if self.all_users || !self.accounts.is_empty() {
+            // Without it sacct only looks at the calling user's jobs.
+            args.push("--allusers".to_string());
+        }
+        if !self.accounts.is_empty() {
+            args.push(format!("--accounts={}", self.accounts.join(",")));
+        }
+        if self.allocations_only {
+            args.push("--allocations".to_string());
+        }
+        args
+    }
+
+    /// Runs sacct through `source` and returns the matching records in sacct's order.
+    pub fn fetch(&self, source: &dyn SlurmSource) -> Result<Vec<AccountingRecord>, SlurmToolError> {
+        let records = parse_sacct(&source.sacct(&self.sacct_args())?)?;
+        Ok(self.select(records))
+    }
+
+    /// Applies the query to already fetched records; sacct does this itself, but recorded
+    /// `--input` dumps hold whatever was captured. States are always checked here because
+    /// `sacct --state` has its own time window rules. Steps are kept or dropped with their
+    /// allocation.
+    pub fn select(&self, records: Vec<AccountingRecord>) -> Vec<AccountingRecord> {
+        let selected: HashSet<String> = records
+            .iter()
+            .filter(|record| !record.is_step() && self.matches(record))
+            .map(|record| record.job_id.clone())
+            .collect();
+
+        records
+            .into_iter()
+            .filter(|record| selected.contains(&record.job_id))
+            .filter(|record| !(self.allocations_only && record.is_step()))
+            .collect()
+    }
+
+    fn matches(&self, record: &AccountingRecord) -> bool {
+        let listed = |list: &[String], value: &Option<String>| {
+            list.is_empty() || value.as_ref().is_some_and(|value| list.contains(value))
+        };
+        let started = record.start_time.or(record.submit_time);
+
+        let in_window = started.is_none_or(|started| started <= self.end)
+            && record.end_time.is_none_or(|ended| ended >= self.start);
+        let job = |wanted: &String| {
+            record.job_id == *wanted
+                || record.job_id.strip_prefix(wanted.as_str()).is_some_and(|task| task.starts_with('_'))
+        };
+
+        listed(&self.users, &record.user)
+            && listed(&self.accounts, &record.account)
+            && (self.states.is_empty()
+                || record
+                    .state
+                    .as_ref()
+                    .is_some_and(|state| self.states.iter().any(|wanted| state.matches(wanted))))
+            && if self.jobs.is_empty() { in_window } else { self.jobs.iter().any(job) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StaticSource;
+
+    const SACCT: &str = "\
+JobID|JobName|User|Account|Partition|State|ExitCode|Elapsed|TotalCPU|MaxRSS|ReqTRES|AllocTRES|NNodes|NCPUS|Timelimit|Submit|Start|End|NodeList
+1001|train|alice|physics|gpu|COMPLETED|0:0|02:00:00|03:30:00||billing=8,cpu=8,mem=32G,node=1,gres/gpu=1|billing=8,cpu=8,mem=32G,node=1,gres/gpu=1|1|8|1-00:00:00|2024-01-01T08:00:00|2024-01-01T08:00:05|2024-01-01T10:00:05|gpu01
+1001.batch|batch||physics||COMPLETED|0:0|02:00:00|03:30:00|20480000K||cpu=8,mem=32G,node=1,gres/gpu=1|1|8||2024-01-01T08:00:05|2024-01-01T08:00:05|2024-01-01T10:00:05|gpu01
+1002_3|sweep|bob|chemistry|cpu|CANCELLED by 1001|0:15|00:10:00|00:01:02.345||billing=4,cpu=4,mem=16G,node=1|billing=4,cpu=4,mem=16G,node=1|1|4|01:00:00|2024-01-02T09:00:00|2024-01-02T09:00:00|2024-01-02T09:10:00|node[01-02]
+1003|queued|alice|physics|gpu|PENDING|0:0|00:00:00|00:00:00||billing=1,cpu=1,node=1||1|1|01:00:00|2024-01-03T09:00:00|Unknown|Unknown|None assigned
+";
+
+    fn everything() -> HistoryQuery {
+        HistoryQuery {
+            jobs: Vec::new(),
+            users: Vec::new(),
+            all_users: true,
+            accounts: Vec::new(),
+            states: Vec::new(),
+            start: parse_timestamp("2024-01-01T00:00:00").unwrap().unwrap(),
+            end: parse_timestamp("2024-01-04T00:00:00").unwrap().unwrap(),
+            allocations_only: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_sacct() {
+        let records = parse_sacct(SACCT).unwrap();
+        assert_eq!(records.len(), 4);
+
+        let step = &records[1];
+        assert_eq!(step.id(), "1001.batch");
+        assert_eq!(step.user.as_deref(), Some("alice"));
+        assert_eq!(step.partition.as_deref(), Some("gpu"));
+        assert_eq!(step.max_rss, Some("20000M".parse().unwrap()));
+        assert_eq!(step.req_tres, None);
+
+        let cancelled = &records[2];
+        assert_eq!(cancelled.state, Some(JobState::Cancelled));
+        assert_eq!(cancelled.exit_code, Some(ExitCode { code: 0, signal: 15 }));
+        assert_eq!(cancelled.total_cpu, Some(SlurmDuration::from_secs(62)));
+        assert_eq!(cancelled.value("nodelist"), Value::Text("node[01-02]".to_string()));
+        assert_eq!(cancelled.value("req_mem"), Value::Memory("16G".parse().unwrap()));
+
+        let fields = [("JobID", "7"), ("ReqMem", "4000Mc"), ("NCPUS", "8"), ("NNodes", "2")];
+        let record = AccountingRecord::from_fields(&fields);
+        assert_eq!(record.requested_memory(), Some("32000M".parse().unwrap()));
+        assert_eq!(record.requested_memory_per_node(), Some("16000M".parse().unwrap()));
+
+        let pending = &records[3];
+        assert_eq!(pending.start_time, None);
+        assert!(pending.nodes.is_empty());
+
+        let error = parse_sacct("JobID|State\n1|COMPLETED|0:0\n").unwrap_err();
+        assert_eq!(error.to_string(), "Expected 2 fields, found 3 (line 2)");
+        assert!(parse_sacct("1|COMPLETED\n").is_err());
+    }
+
+    #[test]
+    fn test_history_query() {
+        let query = HistoryQuery { accounts: vec!["physics".to_string()], ..everything() };
+        let ids = |query: &HistoryQuery| -> Vec<String> {
+            query.fetch(&StaticSource::new(SACCT)).unwrap().iter().map(AccountingRecord::id).collect()
+        };
+        assert_eq!(ids(&query), vec!["1001", "1001.batch", "1003"]);
+
+        let args = query.sacct_args();
+        assert!(args.contains(&"--allusers".to_string()));
+        assert!(args.contains(&"--accounts=physics".to_string()));
+        assert!(args.contains(&"--starttime=2024-01-01T00:00:00".to_string()));
+
+        let query = HistoryQuery { states: vec!["cd".to_string()], allocations_only: true, ..query };
+        assert_eq!(ids(&query), vec!["1001"]);
+
+        let query = HistoryQuery { start: parse_timestamp("2024-01-02T00:00:00").unwrap().unwrap(), ..everything() };
+        assert_eq!(ids(&query), vec!["1002_3", "1003"]);
+
+        let query = HistoryQuery { jobs: vec!["1002".to_string(), "100".to_string()], ..query };
+        assert_eq!(ids(&query), vec!["1002_3"]);
+        assert!(query.sacct_args().contains(&"--jobs=1002,100".to_string()));
+    }
+}
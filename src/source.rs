@@ -0,0 +1,378 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::error::SlurmToolError;
+use crate::json;
+
+/// The kinds of Slurm objects that can be listed with `scontrol show`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity {
+    Node,
+    Partition,
+    Job,
+    Reservation,
+}
+
+impl Entity {
+    /// The argument passed to `scontrol show`.
+    pub fn as_scontrol_arg(&self) -> &'static str {
+        match self {
+            Entity::Node => "node",
+            Entity::Partition => "partition",
+            Entity::Job => "job",
+            Entity::Reservation => "reservation",
+        }
+    }
+
+    /// The key that starts every oneliner record of this entity.
+    pub fn record_key(&self) -> &'static str {
+        match self {
+            Entity::Node => "NodeName",
+            Entity::Partition => "PartitionName",
+            Entity::Job => "JobId",
+            Entity::Reservation => "ReservationName",
+        }
+    }
+}
+
+/// A provider of raw `scontrol show <entity>` output.
+///
+/// The maps (`NodeMap`, `PartitionMap`, `JobMap`, `ReservationMap`) only ever see the text returned here, so the same
+/// parsing code runs against a live cluster, a captured dump, or a pipe. The text is either
+/// `--oneliner` records, one per line, or one or more `--json` documents; the parsers detect
+/// which.
+pub trait SlurmSource {
+    /// Returns the records for `entity`.
+    fn show(&self, entity: Entity) -> Result<String, SlurmToolError>;
+
+    /// Returns `sacct --parsable2` output, header line included, for the selection in `args`.
+    ///
+    /// Sources that replay a dump return the dump's sacct lines and ignore `args`, so callers
+    /// have to apply their selection to the records again. The default fails with
+    /// `SlurmToolError::Unsupported`.
+    fn sacct(&self, _args: &[String]) -> Result<String, SlurmToolError> {
+        Err(SlurmToolError::Unsupported { program: "sacct" })
+    }
+
+    /// Returns `sacctmgr --parsable2 show qos` output, header line included.
+    ///
+    /// Sources that replay a dump return the dump's `Name|...` lines. The default fails with
+    /// `SlurmToolError::Unsupported`.
+    fn sacctmgr_qos(&self) -> Result<String, SlurmToolError> {
+        Err(SlurmToolError::Unsupported { program: "sacctmgr" })
+    }
+}
+
+/// Runs `scontrol` (and `sacct`, `sacctmgr`) on the local machine.
+///
+/// Prefers `--json`, which keeps values containing spaces intact, and falls back to
+/// `--oneliner` for the rest of the run once the local Slurm rejects it.
+#[derive(Debug, Default)]
+pub struct ScontrolSource {
+    oneliner_only: AtomicBool,
+}
+
+impl ScontrolSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A source that never asks for JSON, for Slurm releases without a JSON data parser.
+    pub fn oneliner() -> Self {
+        Self { oneliner_only: AtomicBool::new(true) }
+    }
+
+    fn run(args: &[&str]) -> Result<String, SlurmToolError> {
+        run_command("scontrol", args)
+    }
+}
+
+impl SlurmSource for ScontrolSource {
+    fn show(&self, entity: Entity) -> Result<String, SlurmToolError> {
+        if !self.oneliner_only.load(Ordering::Relaxed) {
+            match Self::run(&["--all", "--json", "show", entity.as_scontrol_arg()]) {
+                Ok(stdout) if json::is_json(&stdout) => return Ok(stdout),
+                // No point retrying without --json
+                Err(e @ SlurmToolError::MissingBinary { .. }) => return Err(e),
+                Ok(_) => log::debug!("scontrol --json returned no JSON, falling back to --oneliner"),
+                Err(e) => log::debug!("scontrol --json failed, falling back to --oneliner: {}", e),
+            }
+            self.oneliner_only.store(true, Ordering::Relaxed);
+        }
+
+        Self::run(&["show", entity.as_scontrol_arg(), "-a", "--oneliner"])
+    }
+
+    fn sacct(&self, args: &[String]) -> Result<String, SlurmToolError> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_command("sacct", &args)
+    }
+
+    fn sacctmgr_qos(&self) -> Result<String, SlurmToolError> {
+        run_command("sacctmgr", &["--parsable2", "--noconvert", "show", "qos"])
+    }
+}
+
+/// Runs a Slurm command and returns its standard output.
+///
+/// A missing binary, a non-zero exit and non UTF-8 output each map to their own
+/// `SlurmToolError` variant so callers can tell "Slurm is not installed" from "the controller
+/// is down".
+pub fn run_command(program: &str, args: &[&str]) -> Result<String, SlurmToolError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|source| match source.kind() {
+            std::io::ErrorKind::NotFound => SlurmToolError::MissingBinary { program: program.to_string() },
+            _ => SlurmToolError::Spawn { program: program.to_string(), source },
+        })?;
+
+    if !output.status.success() {
+        return Err(SlurmToolError::CommandFailed {
+            program: program.to_string(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|source| SlurmToolError::Utf8 { program: program.to_string(), source })
+}
+
+/// Reads captured `scontrol ... --oneliner` dumps from disk.
+///
+/// Node, partition, job and reservation records may live in the same file or in separate
+/// files; each oneliner record is matched to its entity by its leading key (`NodeName=`,
+/// `PartitionName=`, `JobId=` or `ReservationName=`), and each JSON file by its top-level
+/// `nodes`, `partitions`, `jobs` or `reservations` array. A single query cannot mix both
+/// formats.
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    pub paths: Vec<PathBuf>,
+}
+
+impl FileSource {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self { paths }
+    }
+}
+
+impl SlurmSource for FileSource {
+    fn show(&self, entity: Entity) -> Result<String, SlurmToolError> {
+        let mut oneliner = String::new();
+        let mut documents = String::new();
+        for path in &self.paths {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|source| SlurmToolError::Read { path: path.clone(), source })?;
+            if json::is_json(&contents) {
+                documents.push_str(&json::select_documents(&contents, entity)?);
+            } else {
+                oneliner.push_str(&select_records(&contents, entity)?);
+            }
+        }
+
+        match (documents.is_empty(), oneliner.is_empty()) {
+            (false, false) => Err(SlurmToolError::MixedInput { entity: entity.as_scontrol_arg() }),
+            (false, true) => Ok(documents),
+            _ => Ok(oneliner),
+        }
+    }
+
+    fn sacct(&self, _args: &[String]) -> Result<String, SlurmToolError> {
+        let mut records = String::new();
+        for path in &self.paths {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|source| SlurmToolError::Read { path: path.clone(), source })?;
+            records.push_str(&select_parsable(&contents, "JobID|"));
+        }
+        Ok(records)
+    }
+
+    fn sacctmgr_qos(&self) -> Result<String, SlurmToolError> {
+        let mut records = String::new();
+        for path in &self.paths {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|source| SlurmToolError::Read { path: path.clone(), source })?;
+            records.push_str(&select_parsable(&contents, "Name|"));
+        }
+        Ok(records)
+    }
+}
+
+/// Reads a captured dump from standard input.
+///
+/// Stdin can only be consumed once, so the contents are buffered on first use and shared
+/// between the node, partition and job queries.
+#[derive(Debug, Default)]
+pub struct StdinSource {
+    contents: OnceLock<String>,
+}
+
+impl StdinSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn contents(&self) -> Result<&str, SlurmToolError> {
+        if let Some(contents) = self.contents.get() {
+            return Ok(contents);
+        }
+        let mut buffer = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buffer)
+            .map_err(|source| SlurmToolError::Read { path: PathBuf::from("-"), source })?;
+        Ok(self.contents.get_or_init(|| buffer))
+    }
+}
+
+impl SlurmSource for StdinSource {
+    fn show(&self, entity: Entity) -> Result<String, SlurmToolError> {
+        select_records(self.contents()?, entity)
+    }
+
+    fn sacct(&self, _args: &[String]) -> Result<String, SlurmToolError> {
+        Ok(select_parsable(self.contents()?, "JobID|"))
+    }
+
+    fn sacctmgr_qos(&self) -> Result<String, SlurmToolError> {
+        Ok(select_parsable(self.contents()?, "Name|"))
+    }
+}
+
+/// In-memory dump, mostly useful for tests and for embedding fixtures.
+#[derive(Debug, Clone, Default)]
+pub struct StaticSource {
+    pub contents: String,
+}
+
+impl StaticSource {
+    pub fn new(contents: impl Into<String>) -> Self {
+        Self { contents: contents.into() }
+    }
+}
+
+impl SlurmSource for StaticSource {
+    fn show(&self, entity: Entity) -> Result<String, SlurmToolError> {
+        select_records(&self.contents, entity)
+    }
+
+    fn sacct(&self, _args: &[String]) -> Result<String, SlurmToolError> {
+        Ok(select_parsable(&self.contents, "JobID|"))
+    }
+
+    fn sacctmgr_qos(&self) -> Result<String, SlurmToolError> {
+        Ok(select_parsable(&self.contents, "Name|"))
+    }
+}
+
+/// Keeps only the parts of `contents` that describe `entity`.
+///
+/// JSON dumps keep the documents that hold records for `entity`.
+fn select_records(contents: &str, entity: Entity) -> Result<String, SlurmToolError> {
+    if json::is_json(contents) {
+        return json::select_documents(contents, entity);
+    }
+
+    let prefix = format!("{}=", entity.record_key());
+    let mut records = String::new();
+    for line in contents.lines() {
+        if line.trim_start().starts_with(&prefix) {
+            records.push_str(line);
+            records.push('\n');
+        }
+    }
+    Ok(records)
+}
+
+/// Headers that start a `--parsable2` section in a dump: sacct's and sacctmgr's.
+const PARSABLE_HEADERS: [&str; 2] = ["JobID|", "Name|"];
+
+/// Keeps the `--parsable2` sections of `contents` whose header starts with `header`; each
+/// runs while lines are `|` separated and no other section starts.
+fn select_parsable(contents: &str, header: &str) -> String {
+    let mut records = String::new();
+    let mut in_section = false;
+    for line in contents.lines() {
+        if PARSABLE_HEADERS.iter().any(|other| line.starts_with(other)) {
+            in_section = line.starts_with(header);
+        } else if !line.contains('|') {
+            in_section = false;
+        }
+        if in_section {
+            records.push_str(line);
+            records.push('\n');
+        }
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_records() {
+        let dump = "NodeName=node1 Arch=x86_64\n\
+                    PartitionName=debug Nodes=node1\n\
+                    \n\
+                    NodeName=node2 Arch=x86_64\n";
+
+        let source = StaticSource::new(dump);
+
+        assert_eq!(
+            source.show(Entity::Node).unwrap(),
+            "NodeName=node1 Arch=x86_64\nNodeName=node2 Arch=x86_64\n"
+        );
+        assert_eq!(
+            source.show(Entity::Partition).unwrap(),
+            "PartitionName=debug Nodes=node1\n"
+        );
+
+        let source = StaticSource::new(format!("{}JobID|State\n1|COMPLETED\n1.batch|COMPLETED\n", dump));
+        assert_eq!(source.sacct(&[]).unwrap(), "JobID|State\n1|COMPLETED\n1.batch|COMPLETED\n");
+        assert_eq!(source.sacctmgr_qos().unwrap(), "");
+
+        let source = StaticSource::new("JobID|State\n1|COMPLETED\nName|Priority\nnormal|10\n");
+        assert_eq!(source.sacct(&[]).unwrap(), "JobID|State\n1|COMPLETED\n");
+        assert_eq!(source.sacctmgr_qos().unwrap(), "Name|Priority\nnormal|10\n");
+    }
+
+    #[test]
+    fn test_select_json_documents() {
+        let dump = r#"{"nodes": [{"name": "node1"}]}
+                      {"partitions": [{"name": "debug"}]}"#;
+
+        let source = StaticSource::new(dump);
+
+        assert_eq!(source.show(Entity::Node).unwrap(), "{\"nodes\":[{\"name\":\"node1\"}]}\n");
+        assert_eq!(source.show(Entity::Partition).unwrap(), "{\"partitions\":[{\"name\":\"debug\"}]}\n");
+    }
+
+    #[test]
+    fn test_run_command_errors() {
+        assert!(matches!(
+            run_command("slurmtool-no-such-binary", &[]),
+            Err(SlurmToolError::MissingBinary { .. })
+        ));
+        assert!(matches!(
+            run_command("sh", &["-c", "echo down >&2; exit 1"]),
+            Err(SlurmToolError::CommandFailed { code: Some(1), ref stderr, .. }) if stderr == "down\n"
+        ));
+    }
+
+    #[test]
+    fn test_default_accounting_methods() {
+        // Implementations outside this crate only have to provide `show`.
+        struct ShowOnly;
+        impl SlurmSource for ShowOnly {
+            fn show(&self, _entity: Entity) -> Result<String, SlurmToolError> {
+                Ok(String::new())
+            }
+        }
+
+        assert!(matches!(ShowOnly.sacct(&[]), Err(SlurmToolError::Unsupported { program: "sacct" })));
+        assert!(matches!(ShowOnly.sacctmgr_qos(), Err(SlurmToolError::Unsupported { program: "sacctmgr" })));
+    }
+}
@@ -0,0 +1,316 @@
+//! Plain-text tables for the `text` output format.
+//!
+//! Cells keep their typed value so rows sort numerically (`--sort mem_free:desc`) and memory
+//! renders in the `--units` the user picked. When the table is wider than the terminal the
+//! widest columns are truncated with `…`; without a terminal (output piped to a file or
+//! another program) nothing is truncated and no colour is used.
+
+use std::cmp::Ordering;
+use std::io;
+use std::str::FromStr;
+
+use termcolor::{Color, ColorSpec, WriteColor};
+
+use crate::duration::SlurmDuration;
+use crate::error::SlurmToolError;
+use crate::memory::{DisplayUnits, Memory};
+
+/// Columns are never truncated below this many characters (or their header, if shorter).
+const MIN_COLUMN_WIDTH: usize = 6;
+const SEPARATOR: &str = "  ";
+
+/// A typed table value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Empty,
+    Text(String),
+    Count(u64),
+    Float(f64),
+    Memory(Memory),
+    Duration(SlurmDuration),
+}
+
+impl Value {
+    /// `None` becomes `Empty`.
+    pub fn from_option<T>(value: Option<T>, convert: impl FnOnce(T) -> Value) -> Value {
+        value.map_or(Value::Empty, convert)
+    }
+
+    pub fn render(&self, units: DisplayUnits) -> String {
+        match self {
+            Value::Empty => "-".to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Count(count) => count.to_string(),
+            Value::Float(value) => format!("{:.2}", value),
+            Value::Memory(memory) => units.format(*memory),
+            Value::Duration(duration) => duration.to_string(),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Value::Count(_) | Value::Float(_) | Value::Memory(_) | Value::Duration(_))
+    }
+
+    /// Orders values of the same kind naturally; `Empty` sorts first so it ends up last in
+    /// descending order.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Empty, Value::Empty) => Ordering::Equal,
+            (Value::Empty, _) => Ordering::Less,
+            (_, Value::Empty) => Ordering::Greater,
+            (Value::Text(a), Value::Text(b)) => natord::compare(a, b),
+            (Value::Count(a), Value::Count(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::Memory(a), Value::Memory(b)) => a.cmp(b),
+            (Value::Duration(a), Value::Duration(b)) => a.cmp(b),
+            (a, b) => natord::compare(&a.render(DisplayUnits::default()), &b.render(DisplayUnits::default())),
+        }
+    }
+}
+
+/// A value plus an optional foreground colour, used when writing to a terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub value: Value,
+    pub color: Option<Color>,
+}
+
+impl Cell {
+    pub fn new(value: Value) -> Self {
+        Self { value, color: None }
+    }
+
+    pub fn colored(value: Value, color: Color) -> Self {
+        Self { value, color: Some(color) }
+    }
+}
+
+impl From<Value> for Cell {
+    fn from(value: Value) -> Self {
+        Cell::new(value)
+    }
+}
+
+/// One `--sort` key: a column name with an optional `:asc` or `:desc` suffix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (column, direction) = value.trim().split_once(':').unwrap_or((value.trim(), "asc"));
+        let descending = match direction.to_ascii_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => return Err(format!("Invalid sort direction '{}', expected asc or desc", direction)),
+        };
+        if column.is_empty() {
+            return Err(format!("Missing column in sort key '{}'", value));
+        }
+        Ok(SortKey { column: column.to_string(), descending })
+    }
+}
+
+/// Checks that every name in `columns` is one of `available`.
+pub fn check_columns<'a>(
+    columns: impl IntoIterator<Item = &'a str>,
+    available: &[&str],
+) -> Result<(), SlurmToolError> {
+    for column in columns {
+        if !available.contains(&column) {
+            return Err(SlurmToolError::UnknownColumn {
+                column: column.to_string(),
+                available: available.join(", "),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Orders `a` and `b` by `keys` in turn, reading values with `value`.
+pub fn compare_by<T>(a: &T, b: &T, keys: &[SortKey], value: impl Fn(&T, &str) -> Value) -> Ordering {
+    keys.iter()
+        .map(|key| {
+            let ordering = value(a, &key.column).compare(&value(b, &key.column));
+            if key.descending { ordering.reverse() } else { ordering }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// A table whose headers are the upper-cased column names.
+    pub fn new<S: AsRef<str>>(columns: &[S]) -> Self {
+        Self {
+            headers: columns.iter().map(|c| c.as_ref().to_uppercase()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    /// Writes the table, fitting it into `width` characters when given.
+    pub fn write(&self, out: &mut dyn WriteColor, units: DisplayUnits, width: Option<usize>) -> io::Result<()> {
+        let rendered: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.value.render(units)).collect())
+            .collect();
+        let widths = self.column_widths(&rendered, width);
+        let numeric: Vec<bool> = (0..self.headers.len())
+            .map(|i| self.rows.iter().any(|row| row.get(i).is_some_and(|c| c.value.is_numeric())))
+            .collect();
+
+        let header: Vec<Option<&Cell>> = vec![None; self.headers.len()];
+        self.write_row(out, &self.headers, &header, &widths, &numeric)?;
+        for (row, texts) in self.rows.iter().zip(&rendered) {
+            let cells: Vec<Option<&Cell>> = (0..self.headers.len()).map(|i| row.get(i)).collect();
+            self.write_row(out, texts, &cells, &widths, &numeric)?;
+        }
+        Ok(())
+    }
+
+    fn write_row(
+        &self,
+        out: &mut dyn WriteColor,
+        texts: &[String],
+        cells: &[Option<&Cell>],
+        widths: &[usize],
+        numeric: &[bool],
+    ) -> io::Result<()> {
+        let last = widths.len().saturating_sub(1);
+        for (i, &width) in widths.iter().enumerate() {
+            let text = truncate(texts.get(i).map_or("", String::as_str), width);
+            let padding = " ".repeat(width - text.chars().count());
+            if i > 0 {
+                write!(out, "{}", SEPARATOR)?;
+            }
+            if numeric[i] {
+                write!(out, "{}", padding)?;
+            }
+            match cells[i].and_then(|cell| cell.color) {
+                Some(color) => {
+                    out.set_color(ColorSpec::new().set_fg(Some(color)))?;
+                    write!(out, "{}", text)?;
+                    out.reset()?;
+                }
+                None => write!(out, "{}", text)?,
+            }
+            // No trailing spaces after the last column.
+            if !numeric[i] && i != last {
+                write!(out, "{}", padding)?;
+            }
+        }
+        writeln!(out)
+    }
+
+    /// Natural column widths, shrunk widest-first until the row fits into `max_width`.
+    fn column_widths(&self, rendered: &[Vec<String>], max_width: Option<usize>) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in rendered {
+            for (width, text) in widths.iter_mut().zip(row) {
+                *width = (*width).max(text.chars().count());
+            }
+        }
+
+        if let Some(max_width) = max_width {
+            let minimums: Vec<usize> = self
+                .headers
+                .iter()
+                .zip(&widths)
+                .map(|(header, &width)| width.min(MIN_COLUMN_WIDTH.max(header.chars().count())))
+                .collect();
+            let separators = SEPARATOR.len() * widths.len().saturating_sub(1);
+            while widths.iter().sum::<usize>() + separators > max_width {
+                let widest = (0..widths.len())
+                    .filter(|&i| widths[i] > minimums[i])
+                    .max_by_key(|&i| widths[i]);
+                match widest {
+                    Some(i) => widths[i] -= 1,
+                    None => break,
+                }
+            }
+        }
+        widths
+    }
+}
+
+/// Cuts `text` to `width` characters, marking the cut with `…`.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termcolor::NoColor;
+
+    fn render(table: &Table, width: Option<usize>) -> String {
+        let mut out = NoColor::new(Vec::new());
+        table.write(&mut out, DisplayUnits::default(), width).unwrap();
+        String::from_utf8(out.into_inner()).unwrap()
+    }
+
+    fn table() -> Table {
+        let mut table = Table::new(&["name", "mem_free", "reason"]);
+        table.push(vec![
+            Value::Text("node01".to_string()).into(),
+            Value::Memory(Memory::new(64000)).into(),
+            Value::Text("Not responding since the power cut".to_string()).into(),
+        ]);
+        table.push(vec![
+            Value::Text("gpu01".to_string()).into(),
+            Value::Memory(Memory::new(512000)).into(),
+            Value::Empty.into(),
+        ]);
+        table
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(
+            render(&table(), None),
+            "NAME    MEM_FREE  REASON\n\
+             node01  62.5 GiB  Not responding since the power cut\n\
+             gpu01    500 GiB  -\n"
+        );
+        assert_eq!(
+            render(&table(), Some(30)),
+            "NAME    MEM_FREE  REASON\n\
+             node01  62.5 GiB  Not respond…\n\
+             gpu01    500 GiB  -\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let keys: Vec<SortKey> = ["mem_free:desc", "name"].iter().map(|k| k.parse().unwrap()).collect();
+        assert_eq!(keys[0], SortKey { column: "mem_free".to_string(), descending: true });
+        assert!(!keys[1].descending);
+        assert!("mem_free:up".parse::<SortKey>().is_err());
+
+        let mut rows = table().rows;
+        let column = |name: &str| ["name", "mem_free", "reason"].iter().position(|c| *c == name).unwrap();
+        rows.sort_by(|a, b| compare_by(a, b, &keys, |row, name| row[column(name)].value.clone()));
+        assert_eq!(rows[0][0].value, Value::Text("gpu01".to_string()));
+
+        assert!(check_columns(["name", "bogus"], &["name", "state"]).is_err());
+    }
+}
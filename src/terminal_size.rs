@@ -12,9 +12,16 @@ impl TerminalSize {
         Self { width, height }
     }
 
-    pub fn from_terminal_size() -> Self {
-        let (Width(width), Height(height)) = terminal_size().unwrap();
-        Self { width, height }
+    /// The size of the terminal attached to stdout, or `None` when stdout is not a terminal
+    /// (piped, redirected to a file, or running under cron).
+    pub fn from_terminal_size() -> Option<Self> {
+        let (Width(width), Height(height)) = terminal_size()?;
+        Some(Self { width, height })
+    }
+
+    /// The terminal width to fit tables into, `None` when output is not going to a terminal.
+    pub fn table_width() -> Option<usize> {
+        Self::from_terminal_size().map(|size| size.width as usize)
     }
 }
 
@@ -25,7 +32,10 @@ mod tests {
 
     #[test]
     fn test_terminal_size() {
-        let terminal_size = TerminalSize::from_terminal_size();
-        println!("{:?}", terminal_size);
+        // Must not panic without a terminal, e.g. under `cargo test` in CI.
+        if let Some(size) = TerminalSize::from_terminal_size() {
+            assert!(size.width > 0);
+        }
+        assert_eq!(TerminalSize::new(80, 24).width, 80);
     }
-}
\ No newline at end of file
+}
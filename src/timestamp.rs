@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use serde::Serializer;
+
+use crate::duration::SlurmDuration;
+
+/// Parses a timestamp as scontrol prints it (`2024-01-01T08:30:00`, local time).
+///
+/// `None`, `Unknown`, `N/A` and empty values are Slurm's ways of saying "not set" and yield
+/// `Ok(None)`.
+pub fn parse_timestamp(value: &str) -> Result<Option<DateTime<Local>>, String> {
+    let value = value.trim();
+    if matches!(value, "" | "None" | "Unknown" | "N/A" | "(null)" | "0") {
+        return Ok(None);
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .map_err(|e| format!("Invalid timestamp '{}': {}", value, e))?;
+    // Ambiguous local times (DST fold) resolve to the earlier instant.
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(Some)
+        .ok_or_else(|| format!("Timestamp '{}' does not exist in the local time zone", value))
+}
+
+/// Converts a Unix timestamp as found in `--json` output; zero or negative means unset.
+pub fn from_unix(seconds: i64) -> Option<DateTime<Local>> {
+    if seconds <= 0 {
+        return None;
+    }
+    Local.timestamp_opt(seconds, 0).single()
+}
+
+/// Formats the way scontrol does, so values round-trip through `parse_timestamp`.
+pub fn format_timestamp(time: &DateTime<Local>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// Serializes an optional timestamp as RFC 3339 with the local offset, for
+/// `#[serde(serialize_with = "...")]`.
+pub fn serialize_optional<S: Serializer>(
+    time: &Option<DateTime<Local>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&time.to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Time elapsed from `earlier` to `now`; zero if `earlier` is in the future.
+pub fn elapsed(earlier: &DateTime<Local>, now: &DateTime<Local>) -> SlurmDuration {
+    SlurmDuration::from_secs(now.signed_duration_since(earlier).num_seconds().max(0) as u64)
+}
+
+/// A point in time given on the command line: `now`, a local timestamp (`2024-01-01`,
+/// `2024-01-01T08:30`, `2024-01-01T08:30:00`) or how long ago (`7d`, `12h`, `1-00:00:00`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeSpec {
+    Now,
+    At(DateTime<Local>),
+    Ago(SlurmDuration),
+}
+
+impl TimeSpec {
+    pub fn resolve(&self, now: &DateTime<Local>) -> DateTime<Local> {
+        match self {
+            TimeSpec::Now => *now,
+            TimeSpec::At(time) => *time,
+            // `FromStr` rejects durations reaching past the representable range, so this only
+            // clamps when `now` itself is close to its start.
+            TimeSpec::Ago(duration) => {
+                time_before(now, duration).unwrap_or_else(|| DateTime::<Utc>::MIN_UTC.with_timezone(&Local))
+            }
+        }
+    }
+}
+
+impl FromStr for TimeSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let invalid = || {
+            format!("Invalid time '{}', expected now, a date like 2024-01-31T08:00 or a duration like 7d", value)
+        };
+        if value.eq_ignore_ascii_case("now") {
+            return Ok(TimeSpec::Now);
+        }
+
+        let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)));
+        if let Ok(naive) = naive {
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(TimeSpec::At)
+                .ok_or_else(|| format!("Time '{}' does not exist in the local time zone", value));
+        }
+
+        match value.parse::<SlurmDuration>() {
+            Ok(duration) if duration.is_unlimited() => Err(invalid()),
+            Ok(duration) if time_before(&Local::now(), &duration).is_none() => {
+                Err(format!("Duration '{}' reaches back further than any representable time", value))
+            }
+            Ok(duration) => Ok(TimeSpec::Ago(duration)),
+            Err(_) => Err(invalid()),
+        }
+    }
+}
+
+/// `now - duration`, or `None` when the duration is unlimited or the result is out of range.
+fn time_before(now: &DateTime<Local>, duration: &SlurmDuration) -> Option<DateTime<Local>> {
+    let seconds = i64::try_from(duration.as_secs()?).ok()?;
+    now.checked_sub_signed(TimeDelta::try_seconds(seconds)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let time = parse_timestamp("2024-01-01T08:30:00").unwrap().unwrap();
+        assert_eq!(format_timestamp(&time), "2024-01-01T08:30:00");
+
+        for unset in ["None", "Unknown", "N/A", ""] {
+            assert_eq!(parse_timestamp(unset).unwrap(), None, "input: {}", unset);
+        }
+
+        assert!(parse_timestamp("yesterday").is_err());
+        assert_eq!(from_unix(0), None);
+    }
+
+    #[test]
+    fn test_elapsed() {
+        let boot = parse_timestamp("2024-01-01T00:00:00").unwrap().unwrap();
+        let now = parse_timestamp("2024-01-03T02:00:00").unwrap().unwrap();
+
+        assert_eq!(elapsed(&boot, &now).to_string(), "2d2h");
+        assert_eq!(elapsed(&now, &boot), SlurmDuration::Finite(0));
+    }
+
+    #[test]
+    fn test_time_spec() {
+        let now = parse_timestamp("2024-01-10T12:00:00").unwrap().unwrap();
+        let resolve = |value: &str| format_timestamp(&value.parse::<TimeSpec>().unwrap().resolve(&now));
+
+        assert_eq!(resolve("now"), "2024-01-10T12:00:00");
+        assert_eq!(resolve("7d"), "2024-01-03T12:00:00");
+        assert_eq!(resolve("1-12:00:00"), "2024-01-09T00:00:00");
+        assert_eq!(resolve("2024-01-01"), "2024-01-01T00:00:00");
+        assert_eq!(resolve("2024-01-01T08:30"), "2024-01-01T08:30:00");
+        assert!("yesterday".parse::<TimeSpec>().is_err());
+        assert!("UNLIMITED".parse::<TimeSpec>().is_err());
+        assert!("999999999d".parse::<TimeSpec>().is_err());
+        assert!("99999999999999d".parse::<TimeSpec>().is_err());
+
+        let far_back = TimeSpec::Ago(SlurmDuration::from_secs(u64::MAX)).resolve(&now);
+        assert_eq!(far_back, DateTime::<Utc>::MIN_UTC);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::gres::Gres;
+use crate::memory::{Memory, MemoryUnit};
+
+/// A set of trackable resources (TRES) such as `cpu=64,mem=500G,billing=64,gres/gpu=4`.
+///
+/// Counts are kept as-is; memory-like entries (`mem`, `vmem`, `fs/*`, `bb/*`) are normalised
+/// to megabytes so configured and allocated values can be compared regardless of the unit
+/// Slurm happened to print. Serializes as a map of those values.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Tres {
+    pub entries: BTreeMap<String, u64>,
+}
+
+impl Tres {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The value for `key`, e.g. `cpu`, `mem` (megabytes) or `gres/gpu:a100`.
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.entries.get(key).copied()
+    }
+
+    pub fn cpu(&self) -> Option<u64> {
+        self.get("cpu")
+    }
+
+    pub fn mem(&self) -> Option<Memory> {
+        self.get("mem").map(Memory::new)
+    }
+
+    pub fn node(&self) -> Option<u64> {
+        self.get("node")
+    }
+
+    pub fn billing(&self) -> Option<u64> {
+        self.get("billing")
+    }
+
+    /// The count of a GRES, either untyped (`gpu`) or typed (`gpu:a100`).
+    pub fn gres(&self, name: &str) -> Option<u64> {
+        self.get(&format!("gres/{}", name))
+    }
+
+    /// All GRES entries as `(name, type, count)`, e.g. `("gpu", Some("a100"), 4)`.
+    pub fn gres_entries(&self) -> impl Iterator<Item = (&str, Option<&str>, u64)> {
+        self.entries.iter().filter_map(|(key, count)| {
+            let gres = key.strip_prefix("gres/")?;
+            Some(match gres.split_once(':') {
+                Some((name, kind)) => (name, Some(kind), *count),
+                None => (gres, None, *count),
+            })
+        })
+    }
+
+    /// The GRES entries as a list such as `gpu:a100:2`. Untyped totals are left out when typed
+    /// entries of the same GRES exist, since they count the same devices.
+    pub fn gres_list(&self) -> Vec<Gres> {
+        let entries: Vec<_> = self.gres_entries().collect();
+        entries
+            .iter()
+            .filter(|(name, kind, _)| kind.is_some() || !entries.iter().any(|(n, k, _)| n == name && k.is_some()))
+            .map(|(name, kind, count)| Gres::new(name, *kind, *count))
+            .collect()
+    }
+
+    /// An even share of `self` for one of `nodes` nodes, rounded up so no resource rounds
+    /// down to nothing; `node` becomes 1.
+    pub fn per_node(&self, nodes: u64) -> Tres {
+        let nodes = nodes.max(1);
+        let entries = self
+            .entries
+            .iter()
+            .map(|(key, total)| {
+                let share = if key == "node" { 1 } else { total.div_ceil(nodes) };
+                (key.clone(), share)
+            })
+            .collect();
+        Tres { entries }
+    }
+
+    /// Resources left in `self` after taking away `used`; never negative.
+    ///
+    /// Entries only present in `used` are ignored.
+    pub fn saturating_sub(&self, used: &Tres) -> Tres {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(key, total)| (key.clone(), total.saturating_sub(used.get(key).unwrap_or(0))))
+            .collect();
+        Tres { entries }
+    }
+}
+
+/// Whether a TRES value is an amount of memory or storage rather than a count.
+pub fn is_memory_key(key: &str) -> bool {
+    matches!(key, "mem" | "vmem") || key.starts_with("fs/") || key.starts_with("bb/")
+}
+
+/// Parses a size such as `500G`, `62.50G` or `64000` (megabytes) into megabytes.
+pub fn parse_megabytes(value: &str) -> Option<u64> {
+    value.parse::<Memory>().ok().map(|memory| memory.as_mb())
+}
+
+/// Formats in the largest unit that divides `megabytes` exactly, so nothing is rounded.
+fn format_megabytes(megabytes: u64) -> String {
+    let memory = Memory::new(megabytes);
+    [MemoryUnit::P, MemoryUnit::T, MemoryUnit::G]
+        .into_iter()
+        .find(|unit| megabytes > 0 && memory.as_bytes().rem_euclid(unit.bytes()) == 0)
+        .map_or_else(|| format!("{}M", megabytes), |unit| memory.format_in(unit))
+}
+
+impl FromStr for Tres {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut tres = Tres::new();
+        let value = value.trim();
+        if value.is_empty() || value == "(null)" {
+            return Ok(tres);
+        }
+
+        for entry in value.split(',') {
+            let (key, amount) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("TRES entry '{}' has no value", entry))?;
+            let key = key.trim().to_string();
+            let amount = if is_memory_key(&key) {
+                parse_megabytes(amount)
+            } else {
+                amount.trim().parse().ok()
+            }
+            .ok_or_else(|| format!("Invalid value for TRES '{}': '{}'", key, amount))?;
+            tres.entries.insert(key, amount);
+        }
+
+        Ok(tres)
+    }
+}
+
+impl fmt::Display for Tres {
+    /// Formats in Slurm's `key=value,...` form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                if is_memory_key(key) {
+                    format!("{}={}", key, format_megabytes(*value))
+                } else {
+                    format!("{}={}", key, value)
+                }
+            })
+            .collect();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+/// `TRESBillingWeights` such as `CPU=1.0,Mem=0.25G,GRES/gpu=2.0`.
+///
+/// Keys are lower-cased to match TRES keys. A memory weight given per gigabyte (`0.25G`) is
+/// converted to a weight per megabyte, the unit `Tres` stores memory in.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct BillingWeights {
+    pub weights: BTreeMap<String, f64>,
+}
+
+impl BillingWeights {
+    pub fn get(&self, key: &str) -> Option<f64> {
+        self.weights.get(key).copied()
+    }
+
+    /// The billing value of `tres`, summing weight times amount over all weighted entries.
+    pub fn billing(&self, tres: &Tres) -> f64 {
+        self.weights
+            .iter()
+            .map(|(key, weight)| weight * tres.get(key).unwrap_or(0) as f64)
+            .sum()
+    }
+}
+
+impl FromStr for BillingWeights {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut weights = BillingWeights::default();
+        let value = value.trim();
+        if value.is_empty() || value == "(null)" {
+            return Ok(weights);
+        }
+
+        for entry in value.split(',') {
+            let (key, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Billing weight '{}' has no value", entry))?;
+            let key = key.trim().to_lowercase();
+            let weight = weight.trim();
+            // Memory weights are per megabyte unless a unit says otherwise
+            let per_megabyte = match weight.char_indices().last() {
+                Some((i, unit)) if is_memory_key(&key) && unit.is_ascii_alphabetic() => {
+                    let unit = MemoryUnit::from_suffix(&weight[i..])
+                        .ok_or_else(|| format!("Invalid unit in billing weight '{}'", entry))?;
+                    weight[..i].parse::<f64>().map(|w| w / Memory::from_unit(1, unit).in_unit(MemoryUnit::M))
+                }
+                _ => weight.parse::<f64>(),
+            }
+            .map_err(|_| format!("Invalid billing weight '{}'", entry))?;
+            weights.weights.insert(key, per_megabyte);
+        }
+
+        Ok(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tres() {
+        let tres: Tres = "cpu=64,mem=500G,billing=64,gres/gpu=4,gres/gpu:a100=4".parse().unwrap();
+
+        assert_eq!(tres.cpu(), Some(64));
+        assert_eq!(tres.mem(), Some(Memory::new(500 * 1024)));
+        assert_eq!(tres.billing(), Some(64));
+        assert_eq!(tres.gres("gpu"), Some(4));
+        assert_eq!(tres.gres("gpu:a100"), Some(4));
+        assert_eq!(
+            tres.gres_entries().collect::<Vec<_>>(),
+            vec![("gpu", None, 4), ("gpu", Some("a100"), 4)]
+        );
+        assert_eq!(tres.to_string(), "billing=64,cpu=64,gres/gpu=4,gres/gpu:a100=4,mem=500G");
+
+        let alloc: Tres = "cpu=8,mem=62.50G".parse().unwrap();
+        assert_eq!(alloc.mem(), Some(Memory::new(64000)));
+
+        assert!("".parse::<Tres>().unwrap().is_empty());
+        assert!("cpu=abc".parse::<Tres>().is_err());
+        assert!("cpu".parse::<Tres>().is_err());
+    }
+
+    #[test]
+    fn test_free_tres() {
+        let cfg: Tres = "cpu=64,mem=500G,gres/gpu=4".parse().unwrap();
+        let alloc: Tres = "cpu=16,mem=100G,gres/gpu=4,energy=10".parse().unwrap();
+
+        let free = cfg.saturating_sub(&alloc);
+
+        assert_eq!(free.to_string(), "cpu=48,gres/gpu=0,mem=400G");
+    }
+
+    #[test]
+    fn test_per_node_share() {
+        let alloc: Tres = "cpu=96,mem=300G,node=2,gres/gpu=5,gres/gpu:a100=5".parse().unwrap();
+
+        let share = alloc.per_node(2);
+
+        assert_eq!(share.to_string(), "cpu=48,gres/gpu=3,gres/gpu:a100=3,mem=150G,node=1");
+        assert_eq!(share.gres_list(), vec![Gres::new("gpu", Some("a100"), 3)]);
+    }
+
+    #[test]
+    fn test_parse_billing_weights() {
+        let weights: BillingWeights = "CPU=1.0,Mem=0.25G,GRES/gpu=2.0".parse().unwrap();
+
+        assert_eq!(weights.get("cpu"), Some(1.0));
+        assert_eq!(weights.get("mem"), Some(0.25 / 1024.0));
+        assert_eq!(weights.get("gres/gpu"), Some(2.0));
+
+        let tres: Tres = "cpu=4,mem=8G,gres/gpu=1".parse().unwrap();
+        assert_eq!(weights.billing(&tres), 4.0 + 2.0 + 2.0);
+    }
+}
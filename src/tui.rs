@@ -0,0 +1,551 @@
+//! `slurmtool tui`: an interactive cluster dashboard.
+//!
+//! Three panes side by side: the partitions, the nodes of the selected partition with CPU,
+//! memory and GPU utilization bars, and every field and job of the selected node. The data is
+//! re-read from the `SlurmSource` periodically and on `r`; a failed refresh keeps the last
+//! good data on screen and shows the error in the header.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::error::SlurmToolError;
+use crate::filter::NodeFilter;
+use crate::gres::Gres;
+use crate::job::JobMap;
+use crate::memory::DisplayUnits;
+use crate::node::{Node, NodeMap};
+use crate::partition::{PartitionMap, PartitionSummary};
+use crate::source::SlurmSource;
+
+const BAR_WIDTH: usize = 10;
+
+/// Runs the dashboard until the user quits, refreshing every `interval` (never if `None`).
+///
+/// The first load happens before the terminal is taken over, so a broken source fails like
+/// any other subcommand.
+pub fn run(source: &dyn SlurmSource, units: DisplayUnits, interval: Option<Duration>) -> Result<(), SlurmToolError> {
+    let mut app = App::new(units);
+    app.refresh(source)?;
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal, source, interval);
+    ratatui::restore();
+    result.map_err(|e| SlurmToolError::Output(e.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Partitions,
+    Nodes,
+    Detail,
+}
+
+/// The text typed after `/`: a `--where` expression if it parses as one, else a substring of
+/// the node name.
+enum Filter {
+    Expression(NodeFilter),
+    Name(String),
+}
+
+impl Filter {
+    fn parse(text: &str) -> Option<Filter> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        Some(match text.parse() {
+            Ok(filter) => Filter::Expression(filter),
+            Err(_) => Filter::Name(text.to_string()),
+        })
+    }
+
+    fn matches(&self, node: &Node, now: &DateTime<Local>) -> bool {
+        match self {
+            Filter::Expression(filter) => filter.matches(node, now),
+            Filter::Name(name) => node.name.contains(name.as_str()),
+        }
+    }
+}
+
+struct App {
+    units: DisplayUnits,
+    nodes: NodeMap,
+    partitions: PartitionMap,
+    updated: Option<DateTime<Local>>,
+    error: Option<String>,
+    focus: Focus,
+    /// Index 0 is "all nodes", the partitions follow in name order.
+    partition: ListState,
+    node: TableState,
+    detail_scroll: u16,
+    filter: Option<Filter>,
+    filter_text: String,
+    /// The filter being typed, while in filter mode.
+    input: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    fn new(units: DisplayUnits) -> Self {
+        Self {
+            units,
+            nodes: NodeMap::default(),
+            partitions: PartitionMap::default(),
+            updated: None,
+            error: None,
+            focus: Focus::Nodes,
+            partition: ListState::default().with_selected(Some(0)),
+            node: TableState::default().with_selected(Some(0)),
+            detail_scroll: 0,
+            filter: None,
+            filter_text: String::new(),
+            input: None,
+            quit: false,
+        }
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        source: &dyn SlurmSource,
+        interval: Option<Duration>,
+    ) -> io::Result<()> {
+        let mut last_refresh = Instant::now();
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = interval.map_or(Duration::from_secs(3600), |interval| {
+                interval.saturating_sub(last_refresh.elapsed())
+            });
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        if key.code == KeyCode::Char('r') && self.input.is_none() {
+                            last_refresh = Instant::now();
+                            self.refresh_or_report(source);
+                        } else {
+                            self.handle_key(key);
+                        }
+                    }
+                }
+            } else if interval.is_some() {
+                last_refresh = Instant::now();
+                self.refresh_or_report(source);
+            }
+        }
+        Ok(())
+    }
+
+    fn refresh(&mut self, source: &dyn SlurmSource) -> Result<(), SlurmToolError> {
+        let selected = self.selected_node().map(|node| node.name.clone());
+        // Build everything before replacing anything, so a failure keeps the last good data.
+        let mut nodes = NodeMap::build(source)?;
+        nodes.attach_jobs(&JobMap::build(source)?);
+        let partitions = PartitionMap::build(source)?;
+        self.nodes = nodes;
+        self.partitions = partitions;
+        self.updated = Some(Local::now());
+        self.error = None;
+
+        let partitions = self.partitions.partitions.len() + 1;
+        self.partition.select(Some(self.partition.selected().unwrap_or(0).min(partitions - 1)));
+        self.select_node(selected.as_deref());
+        Ok(())
+    }
+
+    fn refresh_or_report(&mut self, source: &dyn SlurmSource) {
+        if let Err(error) = self.refresh(source) {
+            self.error = Some(error.to_string());
+        }
+    }
+
+    /// The partition picked in the list, `None` for "all nodes".
+    fn selected_partition(&self) -> Option<&str> {
+        let index = self.partition.selected()?.checked_sub(1)?;
+        self.partitions.partitions.keys().nth(index).map(String::as_str)
+    }
+
+    fn visible_nodes(&self) -> Vec<&Node> {
+        let now = Local::now();
+        let nodes: Vec<&Node> = match self.selected_partition().and_then(|name| self.partitions.get(name)) {
+            Some(partition) => partition.nodes.iter().filter_map(|name| self.nodes.get(name)).collect(),
+            None => self.nodes.nodes.values().collect(),
+        };
+        nodes
+            .into_iter()
+            .filter(|node| self.filter.as_ref().is_none_or(|filter| filter.matches(node, &now)))
+            .collect()
+    }
+
+    fn selected_node(&self) -> Option<&Node> {
+        self.visible_nodes().get(self.node.selected()?).copied()
+    }
+
+    /// Selects the node called `name` if it is still visible, else keeps the row in range.
+    fn select_node(&mut self, name: Option<&str>) {
+        let visible: Vec<String> = self.visible_nodes().iter().map(|node| node.name.clone()).collect();
+        let index = name
+            .and_then(|name| visible.iter().position(|node| node == name))
+            .unwrap_or_else(|| self.node.selected().unwrap_or(0).min(visible.len().saturating_sub(1)));
+        self.node.select(Some(index));
+        self.detail_scroll = 0;
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let text = self.input.take().unwrap_or_default();
+                    self.filter = Filter::parse(&text);
+                    self.filter_text = text.trim().to_string();
+                    self.node.select(Some(0));
+                    self.select_node(None);
+                }
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if self.filter.is_some() => {
+                self.filter = None;
+                self.filter_text.clear();
+                self.select_node(None);
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('/') => self.input = Some(self.filter_text.clone()),
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = match self.focus {
+                    Focus::Partitions => Focus::Nodes,
+                    Focus::Nodes => Focus::Detail,
+                    Focus::Detail => Focus::Partitions,
+                };
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = match self.focus {
+                    Focus::Partitions => Focus::Detail,
+                    Focus::Nodes => Focus::Partitions,
+                    Focus::Detail => Focus::Nodes,
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let step = |selected: Option<usize>, len: usize| {
+            let selected = selected.unwrap_or(0) as isize;
+            Some(selected.saturating_add(delta).clamp(0, len.saturating_sub(1) as isize) as usize)
+        };
+        match self.focus {
+            Focus::Partitions => {
+                let len = self.partitions.partitions.len() + 1;
+                self.partition.select(step(self.partition.selected(), len));
+                self.node.select(Some(0));
+                self.detail_scroll = 0;
+            }
+            Focus::Nodes => {
+                let len = self.visible_nodes().len();
+                self.node.select(step(self.node.selected(), len));
+                self.detail_scroll = 0;
+            }
+            Focus::Detail => {
+                let scroll = (self.detail_scroll as isize).saturating_add(delta);
+                self.detail_scroll = scroll.clamp(0, u16::MAX as isize) as u16;
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [partitions, nodes, detail] =
+            Layout::horizontal([Constraint::Length(24), Constraint::Min(50), Constraint::Percentage(30)]).areas(body);
+
+        self.draw_header(frame, header);
+        self.draw_partitions(frame, partitions);
+        self.draw_nodes(frame, nodes);
+        self.draw_detail(frame, detail);
+        self.draw_footer(frame, footer);
+    }
+
+    fn block(&self, title: &str, focus: Focus) -> Block<'static> {
+        let style = if self.focus == focus { Style::new().fg(Color::Cyan) } else { Style::new() };
+        Block::new().borders(Borders::ALL).border_style(style).title(format!(" {} ", title))
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = vec![Span::styled("slurmtool", Style::new().add_modifier(Modifier::BOLD))];
+        spans.push(Span::raw(format!(
+            "  {} nodes, {} partitions",
+            self.nodes.nodes.len(),
+            self.partitions.partitions.len()
+        )));
+        if let Some(updated) = self.updated {
+            spans.push(Span::raw(format!("  updated {}", updated.format("%H:%M:%S"))));
+        }
+        if let Some(error) = &self.error {
+            spans.push(Span::styled(format!("  refresh failed: {}", error), Style::new().fg(Color::Red)));
+        }
+        frame.render_widget(Line::from(spans), area);
+    }
+
+    fn draw_partitions(&mut self, frame: &mut Frame, area: Rect) {
+        let mut items = vec![ListItem::new(format!("(all) {}", self.nodes.nodes.len()))];
+        for partition in self.partitions.partitions.values() {
+            let summary = PartitionSummary::new(partition, &self.nodes);
+            let available = summary.nodes.idle + summary.nodes.mixed;
+            let name = if partition.default { format!("{}*", partition.name) } else { partition.name.clone() };
+            items.push(ListItem::new(format!("{} {}/{}", name, available, summary.nodes.total)));
+        }
+
+        let list = List::new(items)
+            .block(self.block("Partitions", Focus::Partitions))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.partition);
+    }
+
+    fn draw_nodes(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .visible_nodes()
+            .into_iter()
+            .map(|node| {
+                let state = node.state.as_ref();
+                let state_style = state.map_or(Style::new(), |s| Style::new().fg(color(s.color())));
+                let (gpus_used, gpus_total) = gpu_usage(node);
+                Row::new(vec![
+                    Line::raw(node.name.clone()),
+                    Line::styled(state.map_or("-".to_string(), |s| s.to_string()), state_style),
+                    bar(node.cpu_alloc.map(u64::from), node.cpu_total.map(u64::from)),
+                    bar(node.allocated_memory.map(|m| m.as_mb()), node.real_memory.map(|m| m.as_mb())),
+                    bar(Some(gpus_used), Some(gpus_total).filter(|total| *total > 0)),
+                ])
+            })
+            .collect();
+
+        let title = match self.selected_partition() {
+            Some(partition) => format!("Nodes in {}", partition),
+            None => "Nodes".to_string(),
+        };
+        let bar_width = BAR_WIDTH as u16 + 4;
+        let table = Table::new(
+            rows,
+            [Constraint::Min(10), Constraint::Length(12), Constraint::Length(bar_width), Constraint::Length(bar_width), Constraint::Length(bar_width)],
+        )
+        .header(Row::new(["NAME", "STATE", "CPU", "MEM", "GPU"]).style(Style::new().add_modifier(Modifier::BOLD)))
+        .block(self.block(&title, Focus::Nodes))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.node);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect) {
+        let lines = match self.selected_node() {
+            Some(node) => detail_lines(node, self.units),
+            None => vec![Line::raw("No node selected")],
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(self.block("Node", Focus::Detail))
+            .scroll((self.detail_scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = match &self.input {
+            Some(input) => Line::from(vec![
+                Span::styled("/", Style::new().fg(Color::Cyan)),
+                Span::raw(format!("{}_", input)),
+                Span::styled("  Enter apply, Esc cancel; an expression like cpu_free>=32 or part of a name", Style::new().fg(Color::DarkGray)),
+            ]),
+            None => {
+                let mut spans = vec![Span::styled(
+                    "q quit  Tab switch pane  ↑↓ move  / filter  r refresh",
+                    Style::new().fg(Color::DarkGray),
+                )];
+                if !self.filter_text.is_empty() {
+                    spans.push(Span::raw(format!("  filter: {} (Esc clears)", self.filter_text)));
+                }
+                Line::from(spans)
+            }
+        };
+        frame.render_widget(line, area);
+    }
+}
+
+/// `██████░░░░ 60%`, green below half, yellow below 80% and red above; `-` without totals.
+fn bar(used: Option<u64>, total: Option<u64>) -> Line<'static> {
+    let (Some(used), Some(total)) = (used, total.filter(|total| *total > 0)) else {
+        return Line::raw("-");
+    };
+    let ratio = (used as f64 / total as f64).clamp(0.0, 1.0);
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+    let color = if ratio < 0.5 {
+        Color::Green
+    } else if ratio < 0.8 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+    Line::from(vec![
+        Span::styled("█".repeat(filled), Style::new().fg(color)),
+        Span::styled("░".repeat(BAR_WIDTH - filled), Style::new().fg(Color::DarkGray)),
+        Span::raw(format!("{:>4}", format!("{}%", (ratio * 100.0).round() as u64))),
+    ])
+}
+
+/// Used and configured GPUs of `node`.
+fn gpu_usage(node: &Node) -> (u64, u64) {
+    let count = |gres: &[Gres]| -> u64 { gres.iter().filter(|g| g.name == "gpu").map(|g| g.count).sum() };
+    let total = count(&node.gres);
+    (total.saturating_sub(count(&node.free_gres())), total)
+}
+
+/// The jobs on the node, then one `field value` line per `Node` field.
+fn detail_lines(node: &Node, units: DisplayUnits) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    if !node.jobs.is_empty() {
+        lines.push(Line::styled("jobs", Style::new().fg(Color::Cyan)));
+        for job in &node.jobs {
+            let user = job.user.as_deref().unwrap_or("-");
+            lines.push(Line::raw(format!("  {} {} {}", job.job_id, user, job.tres)));
+        }
+        lines.push(Line::raw(""));
+    }
+
+    let fields = node.fields(units);
+    let width = fields.iter().map(|(field, _)| field.len()).max().unwrap_or(0);
+    lines.extend(fields.into_iter().map(|(field, value)| {
+        Line::from(vec![
+            Span::styled(format!("{:<width$} ", field, width = width), Style::new().fg(Color::Cyan)),
+            Span::raw(value),
+        ])
+    }));
+    lines
+}
+
+fn color(color: termcolor::Color) -> Color {
+    match color {
+        termcolor::Color::Black => Color::Black,
+        termcolor::Color::Blue => Color::Blue,
+        termcolor::Color::Green => Color::Green,
+        termcolor::Color::Red => Color::Red,
+        termcolor::Color::Cyan => Color::Cyan,
+        termcolor::Color::Magenta => Color::Magenta,
+        termcolor::Color::Yellow => Color::Yellow,
+        _ => Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StaticSource;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    const DUMP: &str = "\
+PartitionName=all Default=YES Nodes=node01,gpu01 State=UP
+PartitionName=gpu Nodes=gpu01 State=UP
+NodeName=gpu01 CPUTot=64 CPUAlloc=48 RealMemory=512000 AllocMem=256000 Gres=gpu:a100:4 GresUsed=gpu:a100:3(IDX:0-2) State=MIXED
+NodeName=node01 CPUTot=32 CPUAlloc=0 RealMemory=256000 AllocMem=0 State=IDLE
+JobId=1001 UserId=alice(1000) JobState=RUNNING NodeList=gpu01 NumNodes=1 AllocTRES=cpu=48,mem=250000M,node=1,gres/gpu=3
+";
+
+    fn app() -> App {
+        let mut app = App::new(DisplayUnits::default());
+        app.refresh(&StaticSource::new(DUMP)).unwrap();
+        app
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn names(app: &App) -> Vec<&str> {
+        app.visible_nodes().iter().map(|node| node.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_navigation_and_filter() {
+        let mut app = app();
+        assert_eq!(names(&app), vec!["gpu01", "node01"]);
+
+        press(&mut app, "j");
+        assert_eq!(app.selected_node().unwrap().name, "node01");
+
+        press(&mut app, "/cpu_free>=20\n");
+        assert_eq!(names(&app), vec!["node01"]);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(names(&app), vec!["gpu01", "node01"]);
+        press(&mut app, "/gpu\n");
+        assert_eq!(names(&app), vec!["gpu01"]);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        // Partitions are listed after "(all)" in name order.
+        press(&mut app, "hjj");
+        assert_eq!(app.selected_partition(), Some("gpu"));
+        assert_eq!(names(&app), vec!["gpu01"]);
+
+        press(&mut app, "q");
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_failed_refresh_keeps_data() {
+        let mut app = app();
+        // New nodes, but a duplicate partition makes the refresh fail
+        let broken = "PartitionName=gpu Nodes=gpu02 State=UP\nPartitionName=gpu Nodes=gpu02 State=UP\n\
+                      NodeName=gpu02 CPUTot=64 CPUAlloc=0 RealMemory=512000 AllocMem=0 State=IDLE\n";
+
+        app.refresh_or_report(&StaticSource::new(broken));
+        assert_eq!(app.error.as_deref(), Some("Duplicate partition name: gpu"));
+        assert_eq!(names(&app), vec!["gpu01", "node01"]);
+        assert_eq!(app.partitions.partitions.len(), 2);
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = app();
+        let mut terminal = Terminal::new(TestBackend::new(140, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("all* 2/2"));
+        assert!(screen.contains("gpu01"));
+        assert!(screen.contains("█████░░░░░ 50%"));
+        assert!(screen.contains("gpu:a100:4"));
+        assert!(screen.contains("real_memory"));
+        assert!(screen.contains("500 GiB"));
+        assert!(screen.contains("1001 alice"));
+    }
+}